@@ -0,0 +1,80 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Parse and write speed on a file as big as the ones ROM managers create.
+//! Run with `cargo bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use steam_shortcuts_editor::shortcuts::{Shortcut, ShortcutProp, Shortcuts};
+
+const ENTRIES: u32 = 10_000;
+
+fn sample() -> Shortcuts {
+    let mut scs = Shortcuts::empty();
+    for n in 0..ENTRIES {
+        let mut sc = Shortcut::empty();
+        let mut set = |k: &str, v: ShortcutProp| {
+            sc.props.insert(k.to_owned(), v);
+        };
+        set(
+            "app_name",
+            ShortcutProp::String(format!("Game number {}", n)),
+        );
+        set(
+            "exe",
+            ShortcutProp::String(format!(
+                "\"/usr/bin/flatpak\" run org.libretro.RetroArch -L cores/snes9x.so \"/roms/snes/Game {}.sfc\"",
+                n
+            )),
+        );
+        set(
+            "start_dir",
+            ShortcutProp::String("\"/usr/bin/\"".to_owned()),
+        );
+        set(
+            "icon",
+            ShortcutProp::String(format!("/roms/snes/media/Game {}.png", n)),
+        );
+        set("allow_overlay", ShortcutProp::UInt32(1));
+        set("last_play_time", ShortcutProp::UInt32(1_660_000_000 + n));
+        set(
+            "tags",
+            ShortcutProp::Strings(vec!["SNES".to_owned(), "favorite".to_owned()]),
+        );
+        let app_id = sc.compute_app_id();
+        sc.props
+            .insert("app_id".to_owned(), ShortcutProp::UInt32(app_id));
+        scs.push(sc);
+    }
+    scs
+}
+
+fn codec(c: &mut Criterion) {
+    let scs = sample();
+    let mut buffer = vec![];
+    scs.store_into(&mut buffer).unwrap();
+
+    let mut group = c.benchmark_group("10k entries");
+    group.throughput(Throughput::Bytes(buffer.len() as u64));
+    group.bench_function("parse", |b| {
+        b.iter(|| Shortcuts::parse(black_box(&buffer), &mut 0).unwrap())
+    });
+    group.bench_function("write", |b| {
+        b.iter(|| {
+            let mut out = Vec::with_capacity(buffer.len());
+            scs.store_into(&mut out).unwrap();
+            out
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, codec);
+criterion_main!(benches);
@@ -0,0 +1,23 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Looking for icons in any bytes must not panic, whatever offsets they
+//! hold.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use steam_shortcuts_editor::pe;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(Some(group)) = pe::largest_icon_group(data) {
+        let _ = pe::icon_file(&group);
+    }
+});
@@ -0,0 +1,32 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Loading any bytes as a shortcuts file must not panic, and whatever loads
+//! must store again to a file that loads and stores to the same bytes.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use steam_shortcuts_editor::shortcuts::Shortcuts;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(scs) = Shortcuts::parse(data, &mut 0) else {
+        return;
+    };
+    let mut once = vec![];
+    scs.store_into(&mut once)
+        .expect("loaded shortcuts must store");
+    let reloaded = Shortcuts::parse(&once, &mut 0).expect("stored shortcuts must load");
+    let mut twice = vec![];
+    reloaded
+        .store_into(&mut twice)
+        .expect("reloaded shortcuts must store");
+    assert_eq!(once, twice);
+});
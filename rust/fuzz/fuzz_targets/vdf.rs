@@ -0,0 +1,32 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Reading any bytes with the generic VDF reader must not panic, a binary
+//! file that reads must be written back to the very same bytes, and every
+//! tree that reads must be writable in every format.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use steam_shortcuts_editor::vdf::{KeyValues, VdfFormat};
+
+fuzz_target!(|data: &[u8]| {
+    let format = VdfFormat::sniff(data);
+    let Ok(kv) = KeyValues::read(data, format) else {
+        return;
+    };
+    if format == VdfFormat::Binary {
+        assert_eq!(kv.to_binary().expect("binary vdf must write back"), data);
+    }
+    for format in [VdfFormat::Binary, VdfFormat::Text, VdfFormat::Json] {
+        // Text and JSON strings may hold a NUL, which binary refuses.
+        let _ = kv.write(format);
+    }
+});
@@ -0,0 +1,263 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use std::cmp::Ordering;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use iter_tools::Itertools;
+
+use crate::shortcuts::{Shortcut, ShortcutProp, ShortcutPropInfo, Shortcuts, SHORTCUT_PROP_INFO};
+use crate::vdf::lossy_all;
+
+/// A field that can be referenced by name from the command line: a known
+/// shortcut property, a column derived from one of them, or a key found in the
+/// file that `SHORTCUT_PROP_INFO` does not describe.
+#[derive(Debug, Clone)]
+pub enum Column {
+    Prop(&'static ShortcutPropInfo),
+    LastPlayTimeFmt,
+    LastPlayTimeIso,
+    LastPlayTimeUtc,
+    Extra(String),
+}
+
+/// Derived columns, in the order they follow their source property.
+pub const DERIVED_COLUMNS: [&str; 3] = [
+    "last_play_time_fmt",
+    "last_play_time_iso",
+    "last_play_time_utc",
+];
+
+impl Column {
+    /// Resolve a column by its switch name (`app_id`), vdf name (`appid`) or
+    /// pascal case name (`AppId`). Derived columns use their switch name only.
+    pub fn parse(name: &str) -> Option<Self> {
+        let lower = name.to_lowercase();
+        match lower.as_str() {
+            "last_play_time_fmt" => return Some(Column::LastPlayTimeFmt),
+            "last_play_time_iso" => return Some(Column::LastPlayTimeIso),
+            "last_play_time_utc" => return Some(Column::LastPlayTimeUtc),
+            _ => (),
+        }
+        SHORTCUT_PROP_INFO
+            .iter()
+            .find(|p| {
+                p.switchname == lower || p.name == lower || p.pascalcase.to_lowercase() == lower
+            })
+            .map(Column::Prop)
+    }
+
+    /// Name used for headers and `--keys` output.
+    pub fn name(&self) -> &str {
+        match self {
+            Column::Prop(p) => p.switchname,
+            Column::LastPlayTimeFmt => DERIVED_COLUMNS[0],
+            Column::LastPlayTimeIso => DERIVED_COLUMNS[1],
+            Column::LastPlayTimeUtc => DERIVED_COLUMNS[2],
+            Column::Extra(k) => k,
+        }
+    }
+
+    /// Property this column reads its value from.
+    pub fn source(&self) -> &str {
+        match self {
+            Column::Prop(p) => p.switchname,
+            Column::Extra(k) => k,
+            _ => "last_play_time",
+        }
+    }
+
+    pub fn prop<'a>(&self, sc: &'a Shortcut) -> &'a ShortcutProp {
+        sc.props.get(self.source()).unwrap_or_default()
+    }
+
+    /// Value as printed by the plain table output: strings are quoted, tags
+    /// are printed as a list.
+    pub fn plain(&self, sc: &Shortcut) -> String {
+        let prop = self.prop(sc);
+        match self {
+            Column::LastPlayTimeFmt | Column::LastPlayTimeIso => {
+                format!("\"{}\"", timestamp(prop))
+            }
+            Column::LastPlayTimeUtc => {
+                format!("\"{}\"", DateTime::<Utc>::from_utc(timestamp(prop), Utc))
+            }
+            _ => match prop {
+                ShortcutProp::UInt32(n) => format!("{}", n),
+                ShortcutProp::String(_) | ShortcutProp::Bytes(_) => format!("\"{}\"", prop.text()),
+                ShortcutProp::Strings(arr) => format!("{:?}", lossy_all(arr)),
+                ShortcutProp::None => String::new(),
+            },
+        }
+    }
+
+    /// Value without any quoting, as shown by the aligned table.
+    pub fn text(&self, sc: &Shortcut) -> String {
+        let prop = self.prop(sc);
+        match self {
+            Column::LastPlayTimeFmt | Column::LastPlayTimeIso => timestamp(prop).to_string(),
+            Column::LastPlayTimeUtc => DateTime::<Utc>::from_utc(timestamp(prop), Utc).to_string(),
+            _ => match prop {
+                ShortcutProp::UInt32(n) => format!("{}", n),
+                ShortcutProp::String(_) | ShortcutProp::Bytes(_) => prop.text().into_owned(),
+                ShortcutProp::Strings(arr) => lossy_all(arr).join(", "),
+                ShortcutProp::None => String::new(),
+            },
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        match self {
+            Column::Prop(p) => matches!(p.type_default, ShortcutProp::UInt32(_)),
+            _ => false,
+        }
+    }
+}
+
+fn timestamp(prop: &ShortcutProp) -> NaiveDateTime {
+    NaiveDateTime::from_timestamp(TryInto::<u32>::try_into(prop).unwrap_or(0) as i64, 0)
+}
+
+/// Keys found in the shortcuts that are not described by `SHORTCUT_PROP_INFO`.
+pub fn extra_keys(scs: &Shortcuts) -> Vec<String> {
+    scs.iter()
+        .flat_map(|sc| sc.extra_props().into_iter().map(|(k, _)| k.clone()))
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// Resolve `--columns` names. Names not known to `SHORTCUT_PROP_INFO` are
+/// accepted only when at least one shortcut carries such a key.
+pub fn resolve(names: &[String], scs: &Shortcuts) -> Result<Vec<Column>, String> {
+    let extra = extra_keys(scs);
+    names.iter().map(|name| resolve_one(name, &extra)).collect()
+}
+
+/// Resolve a single column name against the known props and `extra` keys.
+pub fn resolve_one(name: &str, extra: &[String]) -> Result<Column, String> {
+    let name = name.trim();
+    Column::parse(name)
+        .or_else(|| {
+            let lower = name.to_lowercase();
+            extra.contains(&lower).then_some(Column::Extra(lower))
+        })
+        .ok_or(format!("Unknown column \"{}\".", name))
+}
+
+#[derive(Debug, Clone)]
+pub struct SortKey {
+    pub column: Column,
+    pub desc: bool,
+}
+
+impl SortKey {
+    /// Parse a `field[:asc|desc]` sort specification.
+    pub fn parse(spec: &str, scs: &Shortcuts) -> Result<Self, String> {
+        let (name, dir) = match spec.rsplit_once(':') {
+            Some((name, dir)) => (name, dir),
+            None => (spec, "asc"),
+        };
+        let desc = match dir.to_lowercase().as_str() {
+            "asc" => false,
+            "desc" => true,
+            _ => {
+                return Err(format!(
+                    "Invalid sort direction \"{}\" in \"{}\". Expected \"asc\" or \"desc\".",
+                    dir, spec
+                ))
+            }
+        };
+        Ok(Self {
+            column: resolve_one(name, &extra_keys(scs))?,
+            desc,
+        })
+    }
+}
+
+/// Compare two shortcuts using every key in turn; ties keep index order.
+pub fn compare_by(keys: &[SortKey], a: &Shortcut, b: &Shortcut) -> Ordering {
+    for key in keys {
+        let ord = key.column.prop(a).compare(key.column.prop(b));
+        let ord = if key.desc { ord.reverse() } else { ord };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, last_play_time: u32) -> Shortcut {
+        Shortcut::empty()
+            .with_string("app_name", name)
+            .with_u32("last_play_time", last_play_time)
+    }
+
+    fn with_extra_key() -> Shortcuts {
+        Shortcuts::from_entries([entry("Game", 0).with_string("sortas", "G")])
+    }
+
+    #[test]
+    fn columns_are_found_by_any_of_their_names() {
+        for name in ["app_id", "appid", "AppId", "APPID"] {
+            assert_eq!(Column::parse(name).unwrap().name(), "app_id", "{}", name);
+        }
+        let derived = Column::parse("Last_Play_Time_Iso").unwrap();
+        assert_eq!(derived.name(), "last_play_time_iso");
+        assert_eq!(derived.source(), "last_play_time");
+        assert!(Column::parse("sortas").is_none());
+    }
+
+    #[test]
+    fn extra_keys_resolve_only_when_present() {
+        let scs = with_extra_key();
+        let columns = resolve(&["SortAs".to_owned(), " exe ".to_owned()], &scs).unwrap();
+        assert_eq!(columns[0].name(), "sortas");
+        assert_eq!(columns[1].name(), "exe");
+        assert_eq!(
+            resolve(&["nothing".to_owned()], &scs).unwrap_err(),
+            "Unknown column \"nothing\"."
+        );
+    }
+
+    #[test]
+    fn values_are_quoted_or_plain() {
+        let sc = entry("Game", 0).with_tags(&["a", "b"]);
+        let name = Column::parse("app_name").unwrap();
+        let tags = Column::parse("tags").unwrap();
+        assert_eq!(name.plain(&sc), "\"Game\"");
+        assert_eq!(name.text(&sc), "Game");
+        assert_eq!(tags.plain(&sc), "[\"a\", \"b\"]");
+        assert_eq!(tags.text(&sc), "a, b");
+        assert_eq!(
+            Column::LastPlayTimeIso.text(&entry("Game", 86400)),
+            "1970-01-02 00:00:00"
+        );
+    }
+
+    #[test]
+    fn sort_keys_apply_in_turn_and_keep_ties() {
+        let scs = with_extra_key();
+        let by_time = SortKey::parse("last_play_time:DESC", &scs).unwrap();
+        let by_name = SortKey::parse("app_name", &scs).unwrap();
+        assert!(by_time.desc && !by_name.desc);
+        assert!(SortKey::parse("app_name:up", &scs).is_err());
+
+        let (a, b, c) = (entry("B", 1), entry("A", 1), entry("C", 2));
+        let keys = [by_time, by_name];
+        assert_eq!(compare_by(&keys, &c, &a), Ordering::Less);
+        assert_eq!(compare_by(&keys, &b, &a), Ordering::Less);
+        assert_eq!(compare_by(&keys[..1], &b, &a), Ordering::Equal);
+    }
+}
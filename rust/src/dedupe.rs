@@ -0,0 +1,234 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use std::fmt::Display;
+
+use crate::shortcuts::{Shortcut, ShortcutProp, Shortcuts};
+
+/// What makes two entries of the same file duplicates.
+#[derive(Debug, Clone, Copy)]
+pub enum DedupeKey {
+    /// Same app id. A missing or 0 one is computed like Steam does.
+    AppId,
+    /// Same exe, ignoring quotes, and same launch options, ignoring extra
+    /// spaces.
+    Exe,
+    /// Same name, ignoring case and surrounding spaces.
+    Name,
+}
+
+impl DedupeKey {
+    fn of(&self, sc: &Shortcut) -> Option<String> {
+        let text = |key| sc.prop_to_string(key).unwrap_or_default();
+        match self {
+            DedupeKey::AppId => {
+                let id = match sc.prop_to_u32("app_id") {
+                    None | Some(0) => sc.compute_app_id(),
+                    Some(id) => id,
+                };
+                Some(format!("app_id {}", id))
+            }
+            DedupeKey::Exe => {
+                let exe = text("exe").trim().trim_matches('"').to_owned();
+                let options = text("launch_options")
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+                (!exe.is_empty()).then(|| format!("exe {:?} {:?}", exe, options))
+            }
+            DedupeKey::Name => {
+                let name = text("app_name").trim().to_lowercase();
+                (!name.is_empty()).then(|| format!("name {:?}", name))
+            }
+        }
+    }
+}
+
+/// Which entry of a group survives. Ties go to the lowest index.
+#[derive(Debug, Clone, Copy)]
+pub enum Keep {
+    /// The one with the most recent `last_play_time`.
+    Newest,
+    /// The one with the lowest index.
+    First,
+    /// The one with the most tags.
+    MostTags,
+}
+
+impl Keep {
+    fn rank(&self, sc: &Shortcut) -> usize {
+        match self {
+            Keep::Newest => sc.prop_to_u32("last_play_time").unwrap_or(0) as usize,
+            Keep::First => 0,
+            Keep::MostTags => match sc.props.get("tags") {
+                Some(ShortcutProp::Strings(tags)) => tags.len(),
+                _ => 0,
+            },
+        }
+    }
+}
+
+/// Entries sharing the same key. `entries` holds index and name of each of
+/// them, in index order.
+pub struct Group {
+    pub key: String,
+    pub kept: u32,
+    pub entries: Vec<(u32, String)>,
+}
+
+impl Display for Group {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "= {}", self.key)?;
+        for (index, name) in &self.entries {
+            let action = if *index == self.kept {
+                "keep"
+            } else {
+                "remove"
+            };
+            write!(f, "\n    {:<6} [{}] {:?}", action, index, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Groups of entries sharing the same `key`, in the order of their first
+/// entry. Entries without a key are never grouped.
+pub fn groups(scs: &Shortcuts, key: DedupeKey, keep: Keep) -> Vec<Group> {
+    let mut groups: Vec<(String, Vec<&Shortcut>)> = vec![];
+    for sc in scs.iter() {
+        let Some(k) = key.of(sc) else {
+            continue;
+        };
+        match groups.iter_mut().find(|(g, _)| *g == k) {
+            Some((_, members)) => members.push(sc),
+            None => groups.push((k, vec![sc])),
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(key, members)| {
+            // max_by_key returns the last of the best: on the reversed
+            // members, the one with the lowest index.
+            let kept = members
+                .iter()
+                .rev()
+                .max_by_key(|sc| keep.rank(sc))
+                .and_then(|sc| sc.prop_to_u32("index"))
+                .unwrap_or_default();
+            Group {
+                key,
+                kept,
+                entries: members
+                    .iter()
+                    .map(|sc| {
+                        (
+                            sc.prop_to_u32("index").unwrap_or_default(),
+                            sc.prop_to_string("app_name").unwrap_or_default(),
+                        )
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Remove every entry of `groups` but the kept one, which gets the tags of
+/// the removed ones it did not have. The result is renumbered.
+pub fn dedupe(scs: &mut Shortcuts, groups: &[Group]) {
+    for group in groups {
+        let mut tags = vec![];
+        for (index, _) in &group.entries {
+            if *index == group.kept {
+                continue;
+            }
+            if let Some(ShortcutProp::Strings(t)) = scs
+                .remove(index)
+                .and_then(|sc| sc.props.get("tags").cloned())
+            {
+                tags.extend(t);
+            }
+        }
+        if let Some(kept) = scs.at_mut(&group.kept) {
+            let mut joined = match kept.props.get("tags") {
+                Some(ShortcutProp::Strings(t)) => t.clone(),
+                _ => vec![],
+            };
+            for tag in tags {
+                if !joined.contains(&tag) {
+                    joined.push(tag);
+                }
+            }
+            if !joined.is_empty() {
+                kept.props
+                    .insert("tags".to_owned(), ShortcutProp::Strings(joined));
+            }
+        }
+    }
+    scs.renumber();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, exe: &str, tags: &[&str], last_play_time: u32) -> Shortcut {
+        Shortcut::empty()
+            .with_string("app_name", name)
+            .with_string("exe", exe)
+            .with_tags(tags)
+            .with_u32("last_play_time", last_play_time)
+    }
+
+    #[test]
+    fn exe_ignores_quotes_and_names_ignore_case() {
+        let scs = Shortcuts::from_entries([
+            entry("Game", "/bin/game", &[], 0),
+            entry("Other", "\"/bin/game\"", &[], 0),
+            entry(" game ", "/bin/other", &[], 0),
+        ]);
+        let by_exe = groups(&scs, DedupeKey::Exe, Keep::First);
+        assert_eq!(by_exe.len(), 1);
+        assert_eq!(by_exe[0].entries.len(), 2);
+        let by_name = groups(&scs, DedupeKey::Name, Keep::First);
+        let indices: Vec<u32> = by_name[0].entries.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices, [0, 2]);
+    }
+
+    #[test]
+    fn kept_entry_follows_the_policy_and_ties_go_to_the_lowest_index() {
+        let scs = Shortcuts::from_entries([
+            entry("Game", "/bin/game", &["a"], 5),
+            entry("Game", "/bin/game", &["a", "b"], 9),
+            entry("Game", "/bin/game", &["c", "d"], 9),
+        ]);
+        let kept = |keep| groups(&scs, DedupeKey::Name, keep)[0].kept;
+        assert_eq!(kept(Keep::First), 0);
+        assert_eq!(kept(Keep::Newest), 1);
+        assert_eq!(kept(Keep::MostTags), 1);
+    }
+
+    #[test]
+    fn dedupe_keeps_the_tags_of_removed_entries() {
+        let mut scs = Shortcuts::from_entries([
+            entry("Other", "/bin/other", &[], 0),
+            entry("Game", "/bin/game", &["a"], 0),
+            entry("Game", "/bin/game", &["b", "a"], 0),
+        ]);
+        let found = groups(&scs, DedupeKey::Exe, Keep::First);
+        dedupe(&mut scs, &found);
+        assert_eq!(scs.len(), 2);
+        let game = scs.at(&1).unwrap();
+        assert_eq!(
+            game.props.get("tags"),
+            Some(&ShortcutProp::Strings(vec!["a".to_owned(), "b".to_owned()]))
+        );
+    }
+}
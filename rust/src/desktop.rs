@@ -0,0 +1,605 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Freedesktop `.desktop` launchers, as described by the Desktop Entry
+//! Specification: only the `[Desktop Entry]` group is read.
+
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::lint;
+use crate::shortcuts::{Shortcut, ShortcutProp};
+
+/// Main categories of the Desktop Menu Specification. A launcher listed in
+/// none of them ends up in "Other", exported ones get "Game" instead.
+const MAIN_CATEGORIES: [&str; 13] = [
+    "AudioVideo",
+    "Audio",
+    "Video",
+    "Development",
+    "Education",
+    "Game",
+    "Graphics",
+    "Network",
+    "Office",
+    "Science",
+    "Settings",
+    "System",
+    "Utility",
+];
+
+/// What an exported launcher runs.
+#[derive(Debug, Clone, Copy)]
+pub enum Launch {
+    /// The exe, with its arguments and the launch options.
+    Exe,
+    /// `steam steam://rungameid/<id>`: Steam starts the shortcut, with the
+    /// overlay and the controller configuration.
+    Steam,
+}
+
+/// The 64 bit game id Steam uses in `steam://rungameid/` for a shortcut.
+pub fn game_id(app_id: u32) -> u64 {
+    ((app_id as u64) << 32) | 0x0200_0000
+}
+
+/// App id of `sc`, computed like Steam does when missing or 0.
+fn app_id(sc: &Shortcut) -> u32 {
+    match sc.prop_to_u32("app_id") {
+        None | Some(0) => sc.compute_app_id(),
+        Some(id) => id,
+    }
+}
+
+/// Name of the file exported for `sc`, unique per app id.
+pub fn file_name(sc: &Shortcut) -> String {
+    format!("steam-shortcut-{}.desktop", app_id(sc))
+}
+
+/// Keys of the `[Desktop Entry]` group, in file order. Localized keys
+/// (`Name[it]`) are kept with their locale.
+#[derive(Debug, Default)]
+pub struct DesktopEntry {
+    pub keys: Vec<(String, String)>,
+}
+
+impl DesktopEntry {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut keys = vec![];
+        let mut group = None;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                group = Some(name.to_owned());
+                continue;
+            }
+            if group.as_deref() != Some("Desktop Entry") {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(format!("line {}: \"key=value\" expected", n + 1))?;
+            keys.push((key.trim().to_owned(), unescape(value.trim())));
+        }
+        if group.is_none() || keys.is_empty() {
+            return Err("no [Desktop Entry] group".to_owned());
+        }
+        Ok(Self { keys })
+    }
+
+    /// Value of `key`, the last one if it is repeated.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.keys
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.get(key) == Some("true")
+    }
+
+    /// Entries the desktop menus do not show: deleted or hidden ones.
+    pub fn is_hidden(&self) -> bool {
+        self.flag("Hidden") || self.flag("NoDisplay")
+    }
+
+    /// Shortcut launching this entry, as Steam creates it when a desktop
+    /// launcher is added: quoted program as exe, the other arguments of
+    /// `Exec` as launch options. `source` is stored as `shortcut_path`.
+    pub fn to_shortcut(&self, source: &Path) -> Result<Shortcut, String> {
+        if self.get("Type").unwrap_or("Application") != "Application" {
+            return Err(format!(
+                "type \"{}\" cannot be launched",
+                self.get("Type").unwrap_or_default()
+            ));
+        }
+        let name = self.get("Name").ok_or("no Name")?;
+        let args = split_exec(self.get("Exec").ok_or("no Exec")?)?;
+        let (program, args) = args.split_first().ok_or("empty Exec")?;
+
+        let start_dir = match self.get("Path").filter(|p| !p.is_empty()) {
+            Some(dir) => dir.to_owned(),
+            None => match Path::new(program).parent() {
+                Some(dir) if Path::new(program).is_absolute() => dir.to_string_lossy().into_owned(),
+                _ => "./".to_owned(),
+            },
+        };
+        let icon = self
+            .get("Icon")
+            .filter(|i| Path::new(i).is_absolute())
+            .unwrap_or_default();
+        let tags = self
+            .get("Categories")
+            .unwrap_or_default()
+            .split(';')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        let mut sc = Shortcut::empty();
+        let mut set = |key: &str, value: ShortcutProp| {
+            sc.props.insert(key.to_owned(), value);
+        };
+        set("app_name", ShortcutProp::String(name.to_owned()));
+        set("exe", ShortcutProp::String(format!("\"{}\"", program)));
+        set(
+            "start_dir",
+            ShortcutProp::String(format!("\"{}\"", start_dir)),
+        );
+        set("launch_options", ShortcutProp::String(join_args(args)));
+        set("icon", ShortcutProp::String(icon.to_owned()));
+        set(
+            "shortcut_path",
+            ShortcutProp::String(source.to_string_lossy().into_owned()),
+        );
+        set(
+            "flatpak_app_id",
+            ShortcutProp::String(self.get("X-Flatpak").unwrap_or_default().to_owned()),
+        );
+        set("devkit_game_id", ShortcutProp::String(String::new()));
+        set("allow_desktop_config", ShortcutProp::UInt32(1));
+        set("allow_overlay", ShortcutProp::UInt32(1));
+        set("tags", ShortcutProp::Strings(tags));
+        let app_id = sc.compute_app_id();
+        sc.props
+            .insert("app_id".to_owned(), ShortcutProp::UInt32(app_id));
+        Ok(sc)
+    }
+}
+
+/// Program and arguments `sc` runs: the path of its exe, the arguments
+/// written after it and the launch options.
+pub fn command_line(sc: &Shortcut) -> Result<Vec<String>, String> {
+    let text = |key| {
+        sc.props
+            .get(key)
+            .map(|p| p.text().trim().to_owned())
+            .unwrap_or_default()
+    };
+    let exe = text("exe");
+    let (program, quoted) = lint::split_path(&exe).ok_or("exe has an unclosed quote")?;
+    if program.is_empty() {
+        return Err("no exe".to_owned());
+    }
+    let rest = if quoted {
+        &exe[program.len() + 2..]
+    } else {
+        ""
+    };
+    let mut args = vec![program.to_owned()];
+    args.extend(split_command_line(rest)?);
+    args.extend(split_command_line(&text("launch_options"))?);
+    Ok(args)
+}
+
+impl Display for DesktopEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "[Desktop Entry]")?;
+        for (key, value) in &self.keys {
+            writeln!(f, "{}={}", key, escape(value))?;
+        }
+        Ok(())
+    }
+}
+
+impl DesktopEntry {
+    /// Launcher for `sc`. The icon is the one of the shortcut, or the
+    /// flatpak application id, which names its icon in the icon theme.
+    pub fn from_shortcut(sc: &Shortcut, launch: Launch) -> Result<Self, String> {
+        let text = |key| {
+            sc.props
+                .get(key)
+                .map(|p| p.text().into_owned())
+                .unwrap_or_default()
+        };
+        let name = text("app_name");
+        if name.trim().is_empty() {
+            return Err("no app_name".to_owned());
+        }
+
+        let exec = match launch {
+            Launch::Steam => format!("steam steam://rungameid/{}", game_id(app_id(sc))),
+            Launch::Exe => command_line(sc)?
+                .iter()
+                .map(|a| quote_arg(a).replace('%', "%%"))
+                .collect::<Vec<String>>()
+                .join(" "),
+        };
+
+        let icon = lint::split_path(&text("icon"))
+            .map(|(p, _)| p.to_owned())
+            .filter(|p| !p.is_empty())
+            .unwrap_or_else(|| text("flatpak_app_id"));
+        let mut categories: Vec<String> = match sc.props.get("tags") {
+            Some(ShortcutProp::Strings(tags)) => tags
+                .iter()
+                .filter(|t| {
+                    !t.is_empty() && t.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                })
+                .cloned()
+                .collect(),
+            _ => vec![],
+        };
+        if !categories
+            .iter()
+            .any(|c| MAIN_CATEGORIES.contains(&c.as_str()))
+        {
+            categories.insert(0, "Game".to_owned());
+        }
+
+        let mut keys = vec![
+            ("Type", "Application".to_owned()),
+            ("Version", "1.0".to_owned()),
+            ("Name", name),
+            ("Exec", exec),
+        ];
+        if let (Launch::Exe, Some((dir, _))) = (launch, lint::split_path(&text("start_dir"))) {
+            if !dir.is_empty() && dir != "./" {
+                keys.push(("Path", dir.to_owned()));
+            }
+        }
+        if !icon.trim().is_empty() {
+            keys.push(("Icon", icon));
+        }
+        keys.push(("Terminal", "false".to_owned()));
+        keys.push(("Categories", categories.join(";") + ";"));
+        Ok(Self {
+            keys: keys.into_iter().map(|(k, v)| (k.to_owned(), v)).collect(),
+        })
+    }
+}
+
+/// Escape a string value: `\\`, `\n`, `\t`, `\r` and a leading `\s`.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for (n, c) in value.chars().enumerate() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            ' ' if n == 0 => out.push_str("\\s"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Undo the escapes of string values: `\s`, `\n`, `\t`, `\r` and `\\`.
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(other) => {
+                // Other escapes belong to Exec quoting, keep them for split_exec.
+                if other != '\\' {
+                    out.push('\\');
+                }
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Arguments of an `Exec` value. Double quoted arguments may contain spaces
+/// and the escapes `\"`, `` \` ``, `\$` and `\\`. Field codes (`%f`, `%U`,
+/// ...) are dropped, `%%` is a `%`, and so are the `@@` markers flatpak
+/// adds around file arguments.
+pub fn split_exec(exec: &str) -> Result<Vec<String>, String> {
+    split(exec, true)
+}
+
+/// Arguments of a command line quoted the same way, such as Steam launch
+/// options, where `%` has no special meaning.
+pub fn split_command_line(text: &str) -> Result<Vec<String>, String> {
+    split(text, false)
+}
+
+fn split(text: &str, exec: bool) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut chars = text.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+        let mut arg = String::new();
+        let mut had_field_code = false;
+        if first == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c @ ('"' | '`' | '$' | '\\')) => arg.push(c),
+                        Some(c) => {
+                            arg.push('\\');
+                            arg.push(c);
+                        }
+                        None => return Err("unterminated quote".to_owned()),
+                    },
+                    Some(c) => arg.push(c),
+                    None => return Err("unterminated quote".to_owned()),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                if c == '%' && exec {
+                    match chars.next() {
+                        Some('%') => arg.push('%'),
+                        Some(_) => had_field_code = true,
+                        None => arg.push('%'),
+                    }
+                } else {
+                    arg.push(c);
+                }
+            }
+        }
+        if (had_field_code && arg.is_empty()) || (exec && first != '"' && arg.starts_with("@@")) {
+            continue;
+        }
+        args.push(arg);
+    }
+    Ok(args)
+}
+
+/// Quote `arg` for an `Exec` value or a shell command line, when needed.
+pub fn quote_arg(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        return arg.to_owned();
+    }
+    let mut out = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
+
+fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|a| quote_arg(a))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn exec_arguments_are_unquoted_and_field_codes_dropped() {
+        assert_eq!(
+            split_exec(r#""/opt/My Game/run" --level "a \"b\" \$c" %U 100%%"#).unwrap(),
+            args(&["/opt/My Game/run", "--level", "a \"b\" $c", "100%"])
+        );
+        assert_eq!(
+            split_exec("flatpak run --file-forwarding org.app.Game @@u %U @@").unwrap(),
+            args(&["flatpak", "run", "--file-forwarding", "org.app.Game"])
+        );
+        assert_eq!(split_exec("\"open").unwrap_err(), "unterminated quote");
+        // Launch options have no field codes.
+        assert_eq!(
+            split_command_line("-fps 60%f").unwrap(),
+            args(&["-fps", "60%f"])
+        );
+    }
+
+    #[test]
+    fn only_the_desktop_entry_group_is_read() {
+        let entry = DesktopEntry::parse(
+            "# comment\n\
+             [Desktop Entry]\n\
+             Name=Game\n\
+             Name[it]=Gioco\n\
+             Comment=\\sTwo\\nlines\n\
+             Exec=game\n\
+             Name=Game 2\n\
+             [Desktop Action new]\n\
+             Name=New window\n",
+        )
+        .unwrap();
+        assert_eq!(entry.get("Name"), Some("Game 2"));
+        assert_eq!(entry.get("Name[it]"), Some("Gioco"));
+        assert_eq!(entry.get("Comment"), Some(" Two\nlines"));
+        assert!(!entry.is_hidden());
+
+        assert!(DesktopEntry::parse("[Other]\nName=x\n").is_err());
+        assert_eq!(
+            DesktopEntry::parse("[Desktop Entry]\nName\n").unwrap_err(),
+            "line 2: \"key=value\" expected"
+        );
+    }
+
+    #[test]
+    fn launcher_becomes_a_shortcut_like_steam_makes_it() {
+        let entry = DesktopEntry::parse(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=My Game\n\
+             Exec=\"/opt/My Game/run\" --full %f\n\
+             Icon=my-game\n\
+             Categories=Game;ActionGame;\n\
+             X-Flatpak=org.app.Game\n",
+        )
+        .unwrap();
+        let sc = entry
+            .to_shortcut(Path::new("/usr/share/applications/game.desktop"))
+            .unwrap();
+        let text = |key| sc.prop_to_string(key).unwrap();
+        assert_eq!(text("app_name"), "My Game");
+        assert_eq!(text("exe"), "\"/opt/My Game/run\"");
+        assert_eq!(text("start_dir"), "\"/opt/My Game\"");
+        assert_eq!(text("launch_options"), "--full");
+        // Theme icon names are resolved later, only paths are kept.
+        assert_eq!(text("icon"), "");
+        assert_eq!(text("flatpak_app_id"), "org.app.Game");
+        assert_eq!(
+            text("shortcut_path"),
+            "/usr/share/applications/game.desktop"
+        );
+        assert_eq!(text("tags"), "[\"Game\",\"ActionGame\"]");
+        assert_eq!(sc.prop_to_u32("app_id"), Some(sc.compute_app_id()));
+    }
+
+    #[test]
+    fn links_and_hidden_launchers_are_told_apart() {
+        let link = DesktopEntry::parse("[Desktop Entry]\nType=Link\nName=Site\n").unwrap();
+        assert_eq!(
+            link.to_shortcut(Path::new("site.desktop")).unwrap_err(),
+            "type \"Link\" cannot be launched"
+        );
+        let hidden =
+            DesktopEntry::parse("[Desktop Entry]\nName=x\nExec=x\nNoDisplay=true\n").unwrap();
+        assert!(hidden.is_hidden());
+        let relative = hidden.to_shortcut(Path::new("x.desktop")).unwrap();
+        assert_eq!(relative.prop_to_string("start_dir").unwrap(), "\"./\"");
+    }
+
+    fn shortcut(props: &[(&str, &str)]) -> Shortcut {
+        props.iter().fold(Shortcut::empty(), |sc, (key, value)| {
+            sc.with_string(key, value)
+        })
+    }
+
+    #[test]
+    fn command_line_joins_exe_arguments_and_launch_options() {
+        let sc = shortcut(&[
+            ("exe", "\"/opt/My Game/run\" -a \"b c\""),
+            ("launch_options", "-fps 60"),
+        ]);
+        assert_eq!(
+            command_line(&sc).unwrap(),
+            args(&["/opt/My Game/run", "-a", "b c", "-fps", "60"])
+        );
+        // Unquoted, the whole exe is the program.
+        let sc = shortcut(&[("exe", "/usr/bin/game")]);
+        assert_eq!(command_line(&sc).unwrap(), args(&["/usr/bin/game"]));
+        assert_eq!(command_line(&shortcut(&[])).unwrap_err(), "no exe");
+        assert_eq!(
+            command_line(&shortcut(&[("exe", "\"/opt")])).unwrap_err(),
+            "exe has an unclosed quote"
+        );
+    }
+
+    #[test]
+    fn arguments_are_quoted_only_when_needed() {
+        assert_eq!(quote_arg("--level=1"), "--level=1");
+        assert_eq!(quote_arg(""), "\"\"");
+        assert_eq!(quote_arg("a \"$b\""), r#""a \"\$b\"""#);
+        for arg in ["plain", "with space", "q\"uote", "back\\slash", "$HOME"] {
+            assert_eq!(split_command_line(&quote_arg(arg)).unwrap(), args(&[arg]));
+        }
+    }
+
+    #[test]
+    fn exported_launcher_runs_the_exe_or_steam() {
+        let mut sc = shortcut(&[
+            ("app_name", "My Game"),
+            ("exe", "\"/opt/My Game/run\""),
+            ("start_dir", "\"/opt/My Game\""),
+            ("launch_options", "100%"),
+            ("icon", "\"/opt/My Game/icon.png\""),
+        ]);
+        sc.props.insert(
+            "tags".to_owned(),
+            ShortcutProp::Strings(args(&["RPG", "Not a category"])),
+        );
+        sc.props
+            .insert("app_id".to_owned(), ShortcutProp::UInt32(3_000_000_000));
+
+        assert_eq!(
+            DesktopEntry::from_shortcut(&sc, Launch::Exe)
+                .unwrap()
+                .to_string(),
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Version=1.0\n\
+             Name=My Game\n\
+             Exec=\"/opt/My Game/run\" 100%%\n\
+             Path=/opt/My Game\n\
+             Icon=/opt/My Game/icon.png\n\
+             Terminal=false\n\
+             Categories=Game;RPG;\n"
+        );
+        let steam = DesktopEntry::from_shortcut(&sc, Launch::Steam).unwrap();
+        assert_eq!(
+            steam.get("Exec"),
+            Some(format!("steam steam://rungameid/{}", game_id(3_000_000_000)).as_str())
+        );
+        assert_eq!(steam.get("Path"), None);
+        assert_eq!(file_name(&sc), "steam-shortcut-3000000000.desktop");
+    }
+
+    #[test]
+    fn exported_values_read_back_the_same() {
+        let sc = shortcut(&[
+            ("app_name", " Two\nlines \\ and a tab\t"),
+            ("exe", "/usr/bin/game"),
+            ("flatpak_app_id", "org.app.Game"),
+        ]);
+        let entry = DesktopEntry::from_shortcut(&sc, Launch::Exe).unwrap();
+        let read = DesktopEntry::parse(&entry.to_string()).unwrap();
+        assert_eq!(read.get("Name"), Some(" Two\nlines \\ and a tab\t"));
+        assert_eq!(read.get("Icon"), Some("org.app.Game"));
+        assert_eq!(
+            DesktopEntry::from_shortcut(&shortcut(&[("exe", "x")]), Launch::Exe).unwrap_err(),
+            "no app_name"
+        );
+    }
+}
@@ -0,0 +1,294 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use std::fmt::Display;
+
+use iter_tools::Itertools;
+
+use crate::shortcuts::{Shortcut, ShortcutProp, Shortcuts};
+
+/// A field whose value differs between two matched entries.
+pub struct FieldChange {
+    pub field: String,
+    pub old: ShortcutProp,
+    pub new: ShortcutProp,
+}
+
+pub enum EntryChange<'a> {
+    Added(&'a Shortcut),
+    Removed(&'a Shortcut),
+    Modified {
+        old: &'a Shortcut,
+        new: &'a Shortcut,
+        fields: Vec<FieldChange>,
+    },
+}
+
+/// Ways to pair an entry of the old file with one of the new file, tried in
+/// order. The app id is what Steam uses, the others catch entries whose id
+/// was recomputed after an exe or name change. An app id of 0 is the one
+/// written when it is missing, it tells nothing.
+const MATCHERS: [fn(&Shortcut, &Shortcut) -> bool; 4] = [
+    |a, b| same(a, b, "app_id") && a.prop_to_u32("app_id") != Some(0),
+    |a, b| same(a, b, "app_name") && same(a, b, "exe"),
+    |a, b| same(a, b, "exe"),
+    |a, b| same(a, b, "app_name"),
+];
+
+fn same(a: &Shortcut, b: &Shortcut, key: &str) -> bool {
+    match (a.props.get(key), b.props.get(key)) {
+        (Some(ShortcutProp::None) | None, _) | (_, Some(ShortcutProp::None) | None) => false,
+        (a, b) => a == b,
+    }
+}
+
+/// Compare two files. Entries are listed in the order of the new file, then
+/// the removed ones in the order of the old file. Moving an entry to another
+/// index is not a change.
+pub fn diff<'a>(old: &'a Shortcuts, new: &'a Shortcuts) -> Vec<EntryChange<'a>> {
+    let old_entries: Vec<&Shortcut> = old.iter().collect();
+    let new_entries: Vec<&Shortcut> = new.iter().collect();
+    let mut pairs: Vec<Option<usize>> = vec![None; new_entries.len()];
+    let mut taken = vec![false; old_entries.len()];
+
+    for matcher in MATCHERS {
+        for (n, b) in new_entries.iter().enumerate() {
+            if pairs[n].is_some() {
+                continue;
+            }
+            if let Some(o) =
+                (0..old_entries.len()).find(|o| !taken[*o] && matcher(old_entries[*o], b))
+            {
+                pairs[n] = Some(o);
+                taken[o] = true;
+            }
+        }
+    }
+
+    let mut changes = vec![];
+    for (n, b) in new_entries.iter().enumerate() {
+        match pairs[n] {
+            None => changes.push(EntryChange::Added(b)),
+            Some(o) => {
+                let fields = field_changes(old_entries[o], b);
+                if !fields.is_empty() {
+                    changes.push(EntryChange::Modified {
+                        old: old_entries[o],
+                        new: b,
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    changes.extend(
+        old_entries
+            .iter()
+            .zip(taken)
+            .filter(|(_, taken)| !taken)
+            .map(|(a, _)| EntryChange::Removed(a)),
+    );
+    changes
+}
+
+/// Every field but the index that differs between `old` and `new`, sorted by name.
+pub fn field_changes(old: &Shortcut, new: &Shortcut) -> Vec<FieldChange> {
+    old.props
+        .keys()
+        .chain(new.props.keys())
+        .filter(|k| k.as_str() != "index")
+        .sorted()
+        .dedup()
+        .filter_map(|k| {
+            let a = old.props.get(k).unwrap_or_default();
+            let b = new.props.get(k).unwrap_or_default();
+            (a != b).then(|| FieldChange {
+                field: k.clone(),
+                old: a.clone(),
+                new: b.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Value as shown in change listings: JSON, or `(none)` for a missing field.
+pub fn show(prop: &ShortcutProp) -> String {
+    prop.to_json()
+        .map_or("(none)".to_owned(), |v| v.to_string())
+}
+
+fn label(sc: &Shortcut) -> String {
+    format!("{:?}", sc.prop_to_string("app_name").unwrap_or_default())
+}
+
+fn index(sc: &Shortcut) -> String {
+    sc.prop_to_string("index").unwrap_or_default()
+}
+
+impl Display for EntryChange<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryChange::Added(sc) => write!(f, "+ [{}] {}", index(sc), label(sc)),
+            EntryChange::Removed(sc) => write!(f, "- [{}] {}", index(sc), label(sc)),
+            EntryChange::Modified { old, new, fields } => {
+                let (a, b) = (index(old), index(new));
+                if a == b {
+                    write!(f, "~ [{}] {}", a, label(new))?;
+                } else {
+                    write!(f, "~ [{} → {}] {}", a, b, label(new))?;
+                }
+                for c in fields {
+                    write!(f, "\n    {}: {} → {}", c.field, show(&c.old), show(&c.new))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl EntryChange<'_> {
+    /// `{"change": "added"|"removed"|"modified", ...}`. Added and removed
+    /// changes carry the whole entry, modified ones only the changed fields.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut o = serde_json::Map::new();
+        match self {
+            EntryChange::Added(sc) | EntryChange::Removed(sc) => {
+                let kind = if matches!(self, EntryChange::Added(_)) {
+                    "added"
+                } else {
+                    "removed"
+                };
+                o.insert("change".to_owned(), kind.into());
+                o.insert("entry".to_owned(), serde_json::Value::Object(sc.to_json()));
+            }
+            EntryChange::Modified { old, new, fields } => {
+                o.insert("change".to_owned(), "modified".into());
+                o.insert("old_index".to_owned(), old.prop_to_u32("index").into());
+                o.insert("new_index".to_owned(), new.prop_to_u32("index").into());
+                o.insert("app_name".to_owned(), new.prop_to_string("app_name").into());
+                o.insert(
+                    "fields".to_owned(),
+                    serde_json::Value::Object(
+                        fields
+                            .iter()
+                            .map(|c| {
+                                let mut v = serde_json::Map::new();
+                                v.insert("old".to_owned(), c.old.to_json().into());
+                                v.insert("new".to_owned(), c.new.to_json().into());
+                                (c.field.clone(), serde_json::Value::Object(v))
+                            })
+                            .collect(),
+                    ),
+                );
+            }
+        }
+        serde_json::Value::Object(o)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(app_id: u32, name: &str, exe: &str) -> Shortcut {
+        Shortcut::empty()
+            .with_u32("app_id", app_id)
+            .with_string("app_name", name)
+            .with_string("exe", exe)
+    }
+
+    fn lines(old: &Shortcuts, new: &Shortcuts) -> Vec<String> {
+        diff(old, new).iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn app_id_is_matched_first() {
+        // Same name and exe as entry 1, but the app id of entry 0.
+        let old = Shortcuts::from_entries([entry(1, "A", "/a"), entry(2, "B", "/b")]);
+        let new = Shortcuts::from_entries([entry(1, "B", "/b"), entry(2, "B", "/b")]);
+        assert_eq!(
+            lines(&old, &new),
+            ["~ [0] \"B\"\n    app_name: \"A\" → \"B\"\n    exe: \"/a\" → \"/b\"",]
+        );
+    }
+
+    #[test]
+    fn name_and_exe_then_exe_then_name() {
+        let old = Shortcuts::from_entries([
+            entry(1, "Renamed", "/same"),
+            entry(2, "Moved", "/old"),
+            entry(3, "Both", "/both"),
+        ]);
+        let new = Shortcuts::from_entries([
+            entry(30, "Moved", "/new"),
+            entry(10, "New name", "/same"),
+            entry(20, "Both", "/both"),
+        ]);
+        assert_eq!(
+            lines(&old, &new),
+            [
+                "~ [1 → 0] \"Moved\"\n    app_id: 2 → 30\n    exe: \"/old\" → \"/new\"",
+                "~ [0 → 1] \"New name\"\n    app_id: 1 → 10\n    app_name: \"Renamed\" → \"New name\"",
+                "~ [2] \"Both\"\n    app_id: 3 → 20",
+            ]
+        );
+
+        // The exe wins over the name.
+        let old = Shortcuts::from_entries([entry(1, "X", "/e"), entry(2, "Y", "/f")]);
+        let new = Shortcuts::from_entries([entry(3, "Y", "/e")]);
+        assert_eq!(
+            lines(&old, &new),
+            [
+                "~ [0] \"Y\"\n    app_id: 1 → 3\n    app_name: \"X\" → \"Y\"",
+                "- [1] \"Y\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_app_ids_do_not_match() {
+        let old = Shortcuts::from_entries([entry(0, "A", "/a")]);
+        let new = Shortcuts::from_entries([entry(0, "B", "/b")]);
+        assert_eq!(lines(&old, &new), ["+ [0] \"B\"", "- [0] \"A\""]);
+        // A moved entry with nothing changed is not listed.
+        let moved = Shortcuts::from_entries([entry(0, "B", "/b"), entry(0, "A", "/a")]);
+        assert_eq!(lines(&old, &moved), ["+ [0] \"B\""]);
+    }
+
+    #[test]
+    fn json_changes() {
+        let old = Shortcuts::from_entries([entry(1, "A", "/a"), entry(2, "Gone", "/g")]);
+        let new =
+            Shortcuts::from_entries([entry(1, "A", "/a").with_tags(&["x"]), entry(3, "New", "/n")]);
+        let changes: Vec<serde_json::Value> =
+            diff(&old, &new).iter().map(|c| c.to_json()).collect();
+        assert_eq!(
+            changes,
+            [
+                json!({
+                    "change": "modified",
+                    "old_index": 0,
+                    "new_index": 0,
+                    "app_name": "A",
+                    "fields": {"tags": {"old": null, "new": ["x"]}},
+                }),
+                json!({
+                    "change": "added",
+                    "entry": {"index": 1, "app_id": 3, "app_name": "New", "exe": "/n"},
+                }),
+                json!({
+                    "change": "removed",
+                    "entry": {"index": 1, "app_id": 2, "app_name": "Gone", "exe": "/g"},
+                }),
+            ]
+        );
+    }
+}
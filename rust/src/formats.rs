@@ -0,0 +1,347 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use std::path::Path;
+
+use crate::columns::Column;
+use crate::shortcuts::{Shortcut, ShortcutProp};
+use crate::vdf::lossy_all;
+
+/// Tabular and line based formats used to exchange entries with other tools.
+/// Every reader produces json objects consumed by `Shortcuts::update_from_values`
+/// (JSON) or `Shortcuts::import_records` (the others).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordsFormat {
+    Json,
+    Csv,
+    Tsv,
+    Ndjson,
+    Yaml,
+    Toml,
+}
+
+impl RecordsFormat {
+    fn delimiter(&self) -> u8 {
+        match self {
+            RecordsFormat::Tsv => b'\t',
+            _ => b',',
+        }
+    }
+
+    /// Detect the format from the file extension.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        Some(match ext.as_str() {
+            "json" => RecordsFormat::Json,
+            "csv" => RecordsFormat::Csv,
+            "tsv" => RecordsFormat::Tsv,
+            "ndjson" | "jsonl" => RecordsFormat::Ndjson,
+            "yaml" | "yml" => RecordsFormat::Yaml,
+            "toml" => RecordsFormat::Toml,
+            _ => return None,
+        })
+    }
+
+    /// `--csv-path` accepts both comma and tab separated files.
+    pub fn csv_from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("tsv") => RecordsFormat::Tsv,
+            _ => RecordsFormat::Csv,
+        }
+    }
+}
+
+/// One JSON object per line, with every field of the entry.
+pub fn to_ndjson(entries: &[&Shortcut]) -> String {
+    entries
+        .iter()
+        .map(|sc| serde_json::Value::Object(sc.to_json()).to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// YAML sequence of mappings, with every field of the entry.
+pub fn to_yaml(entries: &[&Shortcut]) -> Result<String, String> {
+    serde_yaml::to_string(&entries.iter().map(|sc| sc.to_json()).collect::<Vec<_>>())
+        .map_err(|e| e.to_string())
+}
+
+/// TOML array of tables named `shortcuts`, with every field of the entry.
+pub fn to_toml(entries: &[&Shortcut]) -> Result<String, String> {
+    let mut root = serde_json::Map::new();
+    root.insert(
+        "shortcuts".to_owned(),
+        serde_json::Value::Array(
+            entries
+                .iter()
+                .map(|sc| serde_json::Value::Object(sc.to_json()))
+                .collect(),
+        ),
+    );
+    toml::to_string(&root).map_err(|e| e.to_string())
+}
+
+/// CSV or TSV with a header row. Tags are joined with `tags_delimiter`.
+pub fn to_delimited(
+    entries: &[&Shortcut],
+    columns: &[Column],
+    format: RecordsFormat,
+    tags_delimiter: &str,
+) -> Result<String, String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(format.delimiter())
+        .from_writer(vec![]);
+    writer
+        .write_record(columns.iter().map(|c| c.name()))
+        .map_err(|e| e.to_string())?;
+    for sc in entries {
+        writer
+            .write_record(columns.iter().map(|c| match (c, c.prop(sc)) {
+                (Column::Prop(_), ShortcutProp::Strings(arr)) => {
+                    lossy_all(arr).join(tags_delimiter)
+                }
+                // Extra lists are written as json arrays, like `edit --val`.
+                (Column::Extra(_), ShortcutProp::Strings(arr)) => {
+                    serde_json::Value::from(lossy_all(arr)).to_string()
+                }
+                _ => c.text(sc),
+            }))
+            .map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+/// Parse `text` into json objects following the field schema of
+/// `SHORTCUT_PROP_INFO`.
+pub fn read_records(
+    text: &str,
+    format: RecordsFormat,
+    tags_delimiter: &str,
+) -> Result<Vec<serde_json::Value>, String> {
+    match format {
+        RecordsFormat::Json => match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(serde_json::Value::Array(a)) => Ok(a),
+            Ok(_) => Err("Input must a be a valid json array.".to_owned()),
+            Err(err) => Err(format!("{}", err)),
+        },
+        RecordsFormat::Ndjson => text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(n, l)| {
+                serde_json::from_str::<serde_json::Value>(l)
+                    .map_err(|e| format!("Line {}: {}", n + 1, e))
+            })
+            .collect(),
+        RecordsFormat::Csv | RecordsFormat::Tsv => {
+            read_delimited(text, format.delimiter(), tags_delimiter)
+        }
+        RecordsFormat::Yaml => serde_yaml::from_str::<serde_json::Value>(text)
+            .map_err(|e| e.to_string())
+            .and_then(records_of),
+        RecordsFormat::Toml => toml::from_str::<serde_json::Value>(text)
+            .map_err(|e| e.to_string())
+            .and_then(records_of),
+    }
+}
+
+/// Entries of a YAML/TOML document: either a top level sequence or a
+/// `shortcuts` key holding it (TOML needs a table at the top).
+fn records_of(value: serde_json::Value) -> Result<Vec<serde_json::Value>, String> {
+    match value {
+        serde_json::Value::Array(a) => Ok(a),
+        serde_json::Value::Object(mut o) => match o.remove("shortcuts") {
+            Some(serde_json::Value::Array(a)) => Ok(a),
+            _ => Err("Input must contain a \"shortcuts\" array of entries.".to_owned()),
+        },
+        _ => Err("Input must be an array of entries.".to_owned()),
+    }
+}
+
+fn read_delimited(
+    text: &str,
+    delimiter: u8,
+    tags_delimiter: &str,
+) -> Result<Vec<serde_json::Value>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(text.as_bytes());
+    // Derived columns (e.g. last_play_time_fmt) are exported for reading only.
+    let headers: Vec<Option<Column>> = reader
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|h| match Column::parse(h) {
+            Some(c @ Column::Prop(_)) => Some(c),
+            Some(_) => None,
+            None => Some(Column::Extra(h.to_lowercase())),
+        })
+        .collect();
+
+    let mut records = vec![];
+    for (n, row) in reader.records().enumerate() {
+        let row = row.map_err(|e| e.to_string())?;
+        let mut object = serde_json::Map::new();
+        for (column, cell) in headers.iter().zip(row.iter()) {
+            let Some(column) = column else { continue };
+            // Entries without an extra key leave its cell empty.
+            if matches!(column, Column::Extra(_)) && cell.is_empty() {
+                continue;
+            }
+            let value = match column {
+                Column::Prop(p) => match &p.type_default {
+                    ShortcutProp::UInt32(_) if cell.trim().is_empty() => continue,
+                    ShortcutProp::UInt32(_) => match cell.trim().parse::<u32>() {
+                        Ok(n) => serde_json::Value::from(n),
+                        Err(_) => {
+                            return Err(format!(
+                                "Row {}: cannot convert \"{}\" of column \"{}\" to u32",
+                                n + 1,
+                                cell,
+                                p.switchname
+                            ))
+                        }
+                    },
+                    ShortcutProp::Strings(_) => serde_json::Value::from(
+                        cell.split(tags_delimiter)
+                            .filter(|t| !t.is_empty())
+                            .collect::<Vec<&str>>(),
+                    ),
+                    _ => serde_json::Value::from(cell),
+                },
+                _ => serde_json::Value::from(cell),
+            };
+            object.insert(column.source().to_owned(), value);
+        }
+        records.push(serde_json::Value::Object(object));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn columns(names: &[&str]) -> Vec<Column> {
+        names
+            .iter()
+            .map(|n| Column::parse(n).unwrap_or(Column::Extra(n.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn delimited_cells_are_quoted_and_read_back() {
+        let sc = Shortcut::empty()
+            .with_u32("app_id", 7)
+            .with_string("app_name", "Say \"hi\", world\nagain")
+            .with_string("exe", "/bin/a\tb");
+        let names = ["app_id", "app_name", "exe"];
+        for format in [RecordsFormat::Csv, RecordsFormat::Tsv] {
+            let text = to_delimited(&[&sc], &columns(&names), format, ";").unwrap();
+            let records = read_records(&text, format, ";").unwrap();
+            assert_eq!(
+                records,
+                [json!({"app_id": 7, "app_name": "Say \"hi\", world\nagain", "exe": "/bin/a\tb"})],
+                "{:?}",
+                format
+            );
+        }
+        let text = to_delimited(&[&sc], &columns(&names), RecordsFormat::Csv, ";").unwrap();
+        assert_eq!(
+            text,
+            "app_id,app_name,exe\n7,\"Say \"\"hi\"\", world\nagain\",/bin/a\tb\n"
+        );
+    }
+
+    #[test]
+    fn tags_are_joined_with_the_delimiter() {
+        let sc = Shortcut::empty().with_tags(&["Action", "Co-op, local"]);
+        let text = to_delimited(&[&sc], &columns(&["tags"]), RecordsFormat::Csv, "|").unwrap();
+        assert_eq!(text, "tags\n\"Action|Co-op, local\"\n");
+        assert_eq!(
+            read_records(&text, RecordsFormat::Csv, "|").unwrap(),
+            [json!({"tags": ["Action", "Co-op, local"]})]
+        );
+        assert_eq!(
+            read_records("tags\n\n", RecordsFormat::Csv, "|").unwrap(),
+            Vec::<serde_json::Value>::new()
+        );
+        assert_eq!(
+            read_records("tags,app_name\n,x\n", RecordsFormat::Csv, "|").unwrap(),
+            [json!({"tags": [], "app_name": "x"})]
+        );
+    }
+
+    #[test]
+    fn empty_cells_of_numbers_and_extra_keys_are_skipped() {
+        let text = "index,is_hidden,sortas,app_name\n0, ,,\n1,1,Zed,B\n";
+        assert_eq!(
+            read_records(text, RecordsFormat::Csv, ";").unwrap(),
+            [
+                json!({"index": 0, "app_name": ""}),
+                json!({"index": 1, "is_hidden": 1, "sortas": "Zed", "app_name": "B"}),
+            ]
+        );
+        assert_eq!(
+            read_records("is_hidden\nyes\n", RecordsFormat::Csv, ";").unwrap_err(),
+            "Row 1: cannot convert \"yes\" of column \"is_hidden\" to u32"
+        );
+    }
+
+    #[test]
+    fn extra_lists_are_json_arrays_and_derived_columns_are_ignored() {
+        let sc = Shortcut::empty()
+            .with_u32("last_play_time", 0)
+            .with("collections", ShortcutProp::Strings(vec!["a;b".to_owned()]));
+        let names = ["collections", "last_play_time_iso"];
+        let text = to_delimited(&[&sc], &columns(&names), RecordsFormat::Csv, ";").unwrap();
+        assert_eq!(
+            text,
+            "collections,last_play_time_iso\n\"[\"\"a;b\"\"]\",1970-01-01 00:00:00\n"
+        );
+        assert_eq!(
+            read_records(&text, RecordsFormat::Csv, ";").unwrap(),
+            [json!({"collections": "[\"a;b\"]"})]
+        );
+    }
+
+    #[test]
+    fn yaml_and_toml_documents_list_their_entries() {
+        let yaml = "- app_name: A\n  is_hidden: true\n  tags: [x, y]\n- AppName: B\n";
+        let shortcuts = "shortcuts:\n  - app_name: A\n";
+        let toml = "[[shortcuts]]\napp_name = \"A\"\nallow_overlay = false\n";
+        assert_eq!(
+            read_records(yaml, RecordsFormat::Yaml, ";").unwrap(),
+            [
+                json!({"app_name": "A", "is_hidden": true, "tags": ["x", "y"]}),
+                json!({"AppName": "B"}),
+            ]
+        );
+        assert_eq!(
+            read_records(shortcuts, RecordsFormat::Yaml, ";").unwrap(),
+            [json!({"app_name": "A"})]
+        );
+        assert_eq!(
+            read_records(toml, RecordsFormat::Toml, ";").unwrap(),
+            [json!({"app_name": "A", "allow_overlay": false})]
+        );
+        assert_eq!(
+            read_records("app_name: A\n", RecordsFormat::Yaml, ";").unwrap_err(),
+            "Input must contain a \"shortcuts\" array of entries."
+        );
+        assert_eq!(
+            read_records("app_name = \"A\"\n", RecordsFormat::Toml, ";").unwrap_err(),
+            "Input must contain a \"shortcuts\" array of entries."
+        );
+        assert!(read_records("- [", RecordsFormat::Yaml, ";").is_err());
+    }
+}
@@ -0,0 +1,424 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Icon names (`org.kde.ark`) to files, following the freedesktop Icon
+//! Theme Specification closely enough for application icons.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::desktop::{command_line, DesktopEntry};
+use crate::shortcuts::Shortcut;
+
+/// Size looked for when none is asked: the one of Steam's library icons.
+pub const DEFAULT_SIZE: u32 = 256;
+
+/// Extensions in order of preference: Steam shows bitmaps, not SVG.
+const EXTENSIONS: [&str; 3] = ["png", "svg", "xpm"];
+
+/// A folder of a theme holding icons of one size, `None` for scalable ones.
+struct SizeDir {
+    path: PathBuf,
+    size: Option<u32>,
+}
+
+pub struct IconResolver {
+    bases: Vec<PathBuf>,
+    /// Themes to look into, in order: the current one, the ones it
+    /// inherits from, hicolor.
+    themes: Vec<String>,
+    size: u32,
+    dirs: HashMap<String, Vec<SizeDir>>,
+}
+
+/// `$XDG_DATA_HOME` and `$XDG_DATA_DIRS`, with the folders where flatpak
+/// exports the icons of its applications.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let mut dirs: Vec<PathBuf> = vec![];
+    match std::env::var_os("XDG_DATA_HOME") {
+        Some(d) if !d.is_empty() => dirs.push(PathBuf::from(d)),
+        _ => dirs.extend(home.iter().map(|h| h.join(".local/share"))),
+    }
+    match std::env::var_os("XDG_DATA_DIRS") {
+        Some(d) if !d.is_empty() => dirs.extend(std::env::split_paths(&d)),
+        _ => dirs.extend(["/usr/local/share", "/usr/share"].map(PathBuf::from)),
+    }
+    let flatpak = home
+        .iter()
+        .map(|h| h.join(".local/share/flatpak/exports/share"))
+        .chain([PathBuf::from("/var/lib/flatpak/exports/share")])
+        .collect::<Vec<PathBuf>>();
+    for dir in flatpak {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// Folders holding icon themes: `~/.icons`, then `icons` of every data dir.
+pub fn icon_dirs() -> Vec<PathBuf> {
+    std::env::var_os("HOME")
+        .map(|h| Path::new(&h).join(".icons"))
+        .into_iter()
+        .chain(data_dirs().into_iter().map(|d| d.join("icons")))
+        .collect()
+}
+
+/// Groups of an ini file, as the ones of icon themes and of the KDE and
+/// GTK settings. Empty when the file cannot be read.
+fn read_ini(path: &Path) -> HashMap<String, HashMap<String, String>> {
+    let mut groups: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return groups;
+    };
+    let mut group = String::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            group = name.to_owned();
+        } else if let Some((k, v)) = line.split_once('=') {
+            groups
+                .entry(group.clone())
+                .or_default()
+                .insert(k.trim().to_owned(), v.trim().to_owned());
+        }
+    }
+    groups
+}
+
+fn ini_value(path: &Path, group: &str, key: &str) -> Option<String> {
+    read_ini(path).get(group)?.get(key).cloned()
+}
+
+/// Icon theme of the desktop, as set in the KDE or GTK settings.
+pub fn current_theme() -> Option<String> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(d) if !d.is_empty() => PathBuf::from(d),
+        _ => Path::new(&std::env::var_os("HOME")?).join(".config"),
+    };
+    ini_value(&config.join("kdeglobals"), "Icons", "Theme")
+        .or_else(|| {
+            ini_value(
+                &config.join("gtk-3.0/settings.ini"),
+                "Settings",
+                "gtk-icon-theme-name",
+            )
+        })
+        .filter(|t| !t.is_empty())
+}
+
+/// Size of the icons of a theme folder, from its name: `48x48`, `48`,
+/// `48x48@2`, or `scalable`.
+fn size_from_name(name: &str) -> Option<Option<u32>> {
+    if name == "scalable" {
+        return Some(None);
+    }
+    let digits: String = name.chars().take_while(char::is_ascii_digit).collect();
+    let rest = &name[digits.len()..];
+    if digits.is_empty() || !(rest.is_empty() || rest.starts_with('x') || rest.starts_with('@')) {
+        return None;
+    }
+    digits.parse().ok().map(Some)
+}
+
+impl IconResolver {
+    /// Resolver looking in the standard folders. Without `theme`, the
+    /// current one of the desktop is used.
+    pub fn new(theme: Option<&str>, size: u32) -> Self {
+        let theme = theme.map(str::to_owned).or_else(current_theme);
+        Self::with_dirs(icon_dirs(), theme.as_deref(), size)
+    }
+
+    /// Resolver looking in the themes of `bases`, then in `pixmaps` next to
+    /// them.
+    pub fn with_dirs(bases: Vec<PathBuf>, theme: Option<&str>, size: u32) -> Self {
+        let mut resolver = Self {
+            bases,
+            themes: vec![],
+            size,
+            dirs: HashMap::new(),
+        };
+        if let Some(theme) = theme {
+            resolver.add_theme(theme);
+        }
+        if !resolver.themes.iter().any(|t| t == "hicolor") {
+            resolver.themes.push("hicolor".to_owned());
+        }
+        resolver
+    }
+
+    /// Add `theme` and, recursively, the ones it inherits from.
+    fn add_theme(&mut self, theme: &str) {
+        if self.themes.iter().any(|t| t == theme) {
+            return;
+        }
+        self.themes.push(theme.to_owned());
+        let inherits = self
+            .bases
+            .iter()
+            .find_map(|b| ini_value(&b.join(theme).join("index.theme"), "Icon Theme", "Inherits"))
+            .unwrap_or_default();
+        for parent in inherits.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            self.add_theme(parent);
+        }
+    }
+
+    /// Every folder of `theme` holding icons of a known size, in every base.
+    /// The sizes come from `index.theme` when it lists the folder, from its
+    /// name otherwise (`48x48/apps`, `apps/48`, `scalable/apps`).
+    fn size_dirs(&mut self, theme: &str) -> &Vec<SizeDir> {
+        if !self.dirs.contains_key(theme) {
+            let mut found = vec![];
+            for base in &self.bases {
+                let root = base.join(theme);
+                let index = read_ini(&root.join("index.theme"));
+                let Ok(entries) = std::fs::read_dir(&root) else {
+                    continue;
+                };
+                for first in entries.filter_map(|e| e.ok()).filter(|e| e.path().is_dir()) {
+                    let first_name = first.file_name().to_string_lossy().into_owned();
+                    let subdirs = std::fs::read_dir(first.path())
+                        .map(|e| {
+                            e.filter_map(|e| e.ok())
+                                .filter(|e| e.path().is_dir())
+                                .collect()
+                        })
+                        .unwrap_or_else(|_| vec![]);
+                    let mut dirs: Vec<(String, PathBuf)> = subdirs
+                        .iter()
+                        .map(|s| {
+                            (
+                                format!("{}/{}", first_name, s.file_name().to_string_lossy()),
+                                s.path(),
+                            )
+                        })
+                        .collect();
+                    dirs.push((first_name.clone(), first.path()));
+                    for (relative, path) in dirs {
+                        let size = match index.get(&relative) {
+                            Some(g) if g.get("Type").is_some_and(|t| t == "Scalable") => Some(None),
+                            Some(g) => g.get("Size").and_then(|s| s.parse().ok()).map(Some),
+                            None => relative.split('/').find_map(size_from_name),
+                        };
+                        if let Some(size) = size {
+                            found.push(SizeDir { path, size });
+                        }
+                    }
+                }
+            }
+            self.dirs.insert(theme.to_owned(), found);
+        }
+        &self.dirs[theme]
+    }
+
+    /// File of the icon called `name`: the one of the first theme having
+    /// it, in the best format and the size closest to the one asked,
+    /// preferring larger ones. An absolute path is returned as is when it
+    /// exists.
+    pub fn resolve(&mut self, name: &str) -> Option<PathBuf> {
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        if name.contains('/') {
+            return Path::new(name).is_file().then(|| PathBuf::from(name));
+        }
+        let name = EXTENSIONS
+            .iter()
+            .find_map(|e| name.strip_suffix(&format!(".{}", e)))
+            .unwrap_or(name);
+
+        let target = self.size;
+        for ext in EXTENSIONS {
+            for theme in self.themes.clone() {
+                let best = self
+                    .size_dirs(&theme)
+                    .iter()
+                    .map(|d| (d.path.join(format!("{}.{}", name, ext)), d.size))
+                    .filter(|(p, _)| p.is_file())
+                    // Exact or larger sizes first, the closest of them; then
+                    // the largest of the smaller ones. Scalable ones last.
+                    .min_by_key(|(_, size)| match size {
+                        Some(s) if *s >= target => (0, s - target),
+                        Some(s) => (1, target - s),
+                        None => (2, 0),
+                    });
+                if let Some((path, _)) = best {
+                    return Some(path);
+                }
+            }
+            let pixmap = self
+                .bases
+                .iter()
+                .filter_map(|b| b.parent())
+                .map(|d| d.join("pixmaps").join(format!("{}.{}", name, ext)))
+                .find(|p| p.is_file());
+            if pixmap.is_some() {
+                return pixmap;
+            }
+        }
+        None
+    }
+}
+
+/// Application id of a `flatpak run [options] <id>` command line.
+fn flatpak_run_id(args: &[String]) -> Option<String> {
+    let program = args.first()?;
+    if !(program == "flatpak" || program.ends_with("/flatpak")) {
+        return None;
+    }
+    let mut rest = args.iter().skip_while(|a| *a != "run").skip(1);
+    rest.find(|a| !a.starts_with('-')).cloned()
+}
+
+/// Names an icon of `sc` may be found by, in order: its `icon` when it is a
+/// theme name, the `Icon` of the launcher at `shortcut_path`, its
+/// `flatpak_app_id` or the application its exe runs with `flatpak run`.
+pub fn icon_names(sc: &Shortcut) -> Vec<String> {
+    let text = |key| {
+        sc.props
+            .get(key)
+            .map(|p| p.text().trim().to_owned())
+            .unwrap_or_default()
+    };
+    let mut names = vec![];
+    let icon = text("icon");
+    if !icon.is_empty() && !icon.contains('/') {
+        names.push(icon);
+    }
+    let launcher = text("shortcut_path");
+    if launcher.ends_with(".desktop") {
+        if let Some(icon) = std::fs::read_to_string(&launcher)
+            .ok()
+            .and_then(|t| DesktopEntry::parse(&t).ok())
+            .and_then(|e| e.get("Icon").map(str::to_owned))
+        {
+            names.push(icon);
+        }
+    }
+    names.push(text("flatpak_app_id"));
+    if let Ok(args) = command_line(sc) {
+        names.extend(flatpak_run_id(&args));
+    }
+    names.retain(|n| !n.is_empty());
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A folder of the system temp dir, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "steam-shortcuts-editor-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, relative: &str, text: &str) -> PathBuf {
+            let path = self.0.join(relative);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, text).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn sizes_come_from_folder_names() {
+        assert_eq!(size_from_name("48x48"), Some(Some(48)));
+        assert_eq!(size_from_name("48"), Some(Some(48)));
+        assert_eq!(size_from_name("48x48@2"), Some(Some(48)));
+        assert_eq!(size_from_name("scalable"), Some(None));
+        assert_eq!(size_from_name("apps"), None);
+        assert_eq!(size_from_name("48px"), None);
+    }
+
+    #[test]
+    fn flatpak_application_is_found_after_run_options() {
+        let id = |list: &[&str]| flatpak_run_id(&args(list));
+        assert_eq!(
+            id(&[
+                "/usr/bin/flatpak",
+                "run",
+                "--branch=stable",
+                "org.app.Game",
+                "-x"
+            ]),
+            Some("org.app.Game".to_owned())
+        );
+        assert_eq!(id(&["flatpak", "install", "org.app.Game"]), None);
+        assert_eq!(id(&["/usr/bin/game", "run", "x"]), None);
+    }
+
+    #[test]
+    fn icon_names_in_order_without_repeats() {
+        let sc = Shortcut::empty()
+            .with_string("icon", "org.app.Game")
+            .with_string("flatpak_app_id", "org.app.Game")
+            .with_string("exe", "\"/usr/bin/flatpak\"")
+            .with_string("launch_options", "run --branch=stable org.app.Other");
+        assert_eq!(icon_names(&sc), args(&["org.app.Game", "org.app.Other"]));
+    }
+
+    #[test]
+    fn themes_sizes_and_formats_are_looked_up_in_order() {
+        let tmp = TempDir::new("icons");
+        tmp.write(
+            "icons/Mine/index.theme",
+            "[Icon Theme]\nInherits=Parent\n\n[apps/big]\nSize=512\n",
+        );
+        let big = tmp.write("icons/Mine/apps/big/game.png", "");
+        let small = tmp.write("icons/Mine/48x48/apps/game.png", "");
+        tmp.write("icons/Mine/scalable/apps/game.svg", "");
+        tmp.write("icons/Mine/scalable/apps/vector.svg", "");
+        let bitmap = tmp.write("icons/hicolor/16x16/apps/vector.png", "");
+        let inherited = tmp.write("icons/Parent/256/apps/other.png", "");
+        let pixmap = tmp.write("pixmaps/old.xpm", "");
+        let bases = vec![tmp.0.join("icons")];
+
+        let mut resolver = IconResolver::with_dirs(bases.clone(), Some("Mine"), 256);
+        assert_eq!(resolver.resolve("game"), Some(big.clone()));
+        assert_eq!(resolver.resolve("game.png"), Some(big));
+        assert_eq!(resolver.resolve("other"), Some(inherited));
+        // A bitmap of any theme is better than an SVG.
+        assert_eq!(resolver.resolve("vector"), Some(bitmap));
+        assert_eq!(resolver.resolve("old"), Some(pixmap.clone()));
+        assert_eq!(resolver.resolve("missing"), None);
+        let absolute = pixmap.to_string_lossy().into_owned();
+        assert_eq!(resolver.resolve(&absolute), Some(pixmap));
+
+        let mut resolver = IconResolver::with_dirs(bases.clone(), Some("Mine"), 32);
+        assert_eq!(resolver.resolve("game"), Some(small));
+
+        // Without a theme only hicolor is looked into.
+        let mut resolver = IconResolver::with_dirs(bases, None, 256);
+        assert_eq!(resolver.resolve("game"), None);
+    }
+}
@@ -0,0 +1,269 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use std::io::IsTerminal;
+
+use crate::shortcuts::{consume_byte, consume_bytes, consume_string, consume_u32};
+use crate::vdf::MAX_DEPTH;
+
+/// Bytes of context dumped around the offset where parsing stopped.
+const CONTEXT_BYTES: usize = 32;
+
+/// Where and why the walk stopped before the end of the file.
+pub struct Problem {
+    pub offset: usize,
+    pub message: String,
+}
+
+/// Print the structure of a binary vdf file, one item per line, reading it
+/// with the same functions used by `Shortcuts::parse` so the walk stops
+/// exactly where loading would fail.
+pub fn inspect(buffer: &[u8]) -> Result<(), Problem> {
+    let highlight = std::io::stdout().is_terminal();
+    let mut index = 0;
+    let result = walk_map(buffer, &mut index, 0).and_then(|_| {
+        if index < buffer.len() {
+            Err(Problem {
+                offset: index,
+                message: format!(
+                    "{} unexpected bytes after the end of the root map",
+                    buffer.len() - index
+                ),
+            })
+        } else {
+            Ok(())
+        }
+    });
+
+    if let Err(problem) = &result {
+        let line = format!(
+            "0x{:06x}  !! {}, parsing stops here",
+            problem.offset, problem.message
+        );
+        if highlight {
+            println!("\x1b[1;31m{}\x1b[0m", line);
+        } else {
+            println!("{}", line);
+        }
+        let start = problem.offset.saturating_sub(CONTEXT_BYTES / 2) & !0xf;
+        let end = (start + CONTEXT_BYTES).min(buffer.len());
+        for row in (start..end).step_by(16) {
+            println!(
+                "0x{:06x}  {}",
+                row,
+                (row..(row + 16).min(end))
+                    .map(|i| {
+                        let byte = format!("{:02x}", buffer[i]);
+                        if i == problem.offset && highlight {
+                            format!("\x1b[1;31m{}\x1b[0m", byte)
+                        } else if i == problem.offset {
+                            format!("[{}]", byte)
+                        } else {
+                            byte
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            );
+        }
+    } else {
+        println!("0x{:06x}  end of file, {} bytes", index, buffer.len());
+    }
+    result
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn type_name(type_byte: u8) -> Option<&'static str> {
+    Some(match type_byte {
+        0x00 => "map",
+        0x01 => "string",
+        0x02 => "int32",
+        0x08 => "end",
+        _ => return None,
+    })
+}
+
+/// Names of the binary VDF types `Shortcuts::parse` does not read.
+fn unsupported_type_name(type_byte: u8) -> Option<&'static str> {
+    Some(match type_byte {
+        0x03 => "float",
+        0x04 => "pointer",
+        0x05 => "wstring",
+        0x06 => "color",
+        0x07 => "uint64",
+        0x0A => "int64",
+        _ => return None,
+    })
+}
+
+fn read_string(buffer: &[u8], index: &mut usize, what: &str) -> Result<String, Problem> {
+    let start = *index;
+    consume_string(buffer, index).ok_or(Problem {
+        offset: start,
+        message: if start >= buffer.len() {
+            format!("unexpected end of file, {} expected", what)
+        } else {
+            format!("unterminated {}", what)
+        },
+    })
+}
+
+fn walk_map(buffer: &[u8], index: &mut usize, depth: usize) -> Result<(), Problem> {
+    let indent = "  ".repeat(depth);
+    loop {
+        let offset = *index;
+        let type_byte = consume_byte(buffer, index).ok_or(Problem {
+            offset,
+            message: "unexpected end of file, type byte or map end expected".to_owned(),
+        })?;
+        let Some(name) = type_name(type_byte) else {
+            return Err(Problem {
+                offset,
+                message: match unsupported_type_name(type_byte) {
+                    Some(n) => format!("type byte 0x{:02x} ({}) is not supported", type_byte, n),
+                    None => format!("unknown type byte 0x{:02x}", type_byte),
+                },
+            });
+        };
+        if type_byte == 0x08 {
+            println!("0x{:06x}  08 {}", offset, name);
+            return Ok(());
+        }
+
+        let key = read_string(buffer, index, "key")?;
+        let value_offset = *index;
+        match type_byte {
+            0x00 => {
+                println!("0x{:06x}  00 {:<6} {}{:?}", offset, name, indent, key);
+                if depth >= MAX_DEPTH {
+                    return Err(Problem {
+                        offset,
+                        message: format!("maps nested deeper than {} levels", MAX_DEPTH),
+                    });
+                }
+                walk_map(buffer, index, depth + 1)?;
+            }
+            0x01 => {
+                let value = consume_bytes(buffer, index).ok_or(Problem {
+                    offset: value_offset,
+                    message: "unterminated string value".to_owned(),
+                })?;
+                println!(
+                    "0x{:06x}  01 {:<6} {}{:?} = {:?}{}  [{}]",
+                    offset,
+                    name,
+                    indent,
+                    key,
+                    String::from_utf8_lossy(&value),
+                    if std::str::from_utf8(&value).is_err() {
+                        " (not UTF-8)"
+                    } else {
+                        ""
+                    },
+                    hex(&buffer[value_offset..*index])
+                );
+            }
+            _ => {
+                let value = consume_u32(buffer, index).ok_or(Problem {
+                    offset: value_offset,
+                    message: "unexpected end of file, 4 bytes int32 expected".to_owned(),
+                })?;
+                println!(
+                    "0x{:06x}  02 {:<6} {}{:?} = {}  [{}]",
+                    offset,
+                    name,
+                    indent,
+                    key,
+                    value,
+                    hex(&buffer[value_offset..*index])
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_file_is_walked_to_the_end() {
+        assert!(inspect(include_bytes!("../tests/corpus/minimal.vdf")).is_ok());
+        assert!(inspect(include_bytes!("../tests/corpus/non_utf8.vdf")).is_ok());
+    }
+
+    #[test]
+    fn truncated_file_stops_at_its_end() {
+        let bytes = include_bytes!("../tests/corpus/minimal.vdf");
+        let cut = &bytes[..bytes.len() - 3];
+        let problem = inspect(cut).unwrap_err();
+        assert_eq!(problem.offset, cut.len());
+        assert!(
+            problem.message.contains("end of file"),
+            "{}",
+            problem.message
+        );
+    }
+
+    #[test]
+    fn unsupported_type_is_named() {
+        let problem = inspect(b"\x00shortcuts\x00\x07big\x00").unwrap_err();
+        assert_eq!(problem.offset, 11);
+        assert_eq!(problem.message, "type byte 0x07 (uint64) is not supported");
+
+        let problem = inspect(b"\x00shortcuts\x00\x09k\x00").unwrap_err();
+        assert_eq!(problem.message, "unknown type byte 0x09");
+    }
+
+    #[test]
+    fn bytes_after_the_root_map_are_reported() {
+        let problem = inspect(b"\x00shortcuts\x00\x08\x08junk").unwrap_err();
+        assert_eq!(problem.offset, 13);
+        assert_eq!(
+            problem.message,
+            "4 unexpected bytes after the end of the root map"
+        );
+    }
+
+    #[test]
+    fn unterminated_key_is_reported_where_it_starts() {
+        let problem = inspect(b"\x00shortcuts\x00\x01app").unwrap_err();
+        assert_eq!(problem.offset, 12);
+        assert_eq!(problem.message, "unterminated key");
+    }
+
+    #[test]
+    fn hex_dump() {
+        assert_eq!(hex(b"\x00\x0a\xff"), "00 0a ff");
+        assert_eq!(hex(b""), "");
+    }
+
+    #[test]
+    fn deep_nesting_stops_at_the_depth_limit() {
+        let mut bytes = b"\x00shortcuts\x00".to_vec();
+        for _ in 0..200_000 {
+            bytes.extend(b"\x00k\x00");
+        }
+        let problem = inspect(&bytes).unwrap_err();
+        // The root map is at depth 0, so the one at depth MAX_DEPTH is rejected.
+        assert_eq!(problem.offset, 11 + 3 * (MAX_DEPTH - 1));
+        assert_eq!(
+            problem.message,
+            format!("maps nested deeper than {} levels", MAX_DEPTH)
+        );
+    }
+}
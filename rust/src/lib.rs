@@ -0,0 +1,30 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Reading, editing and writing of Steam `shortcuts.vdf` files. The command
+//! line tool in `main.rs` is built on top of these modules.
+
+pub mod columns;
+pub mod dedupe;
+pub mod desktop;
+pub mod diff;
+pub mod formats;
+pub mod icons;
+pub mod inspect;
+pub mod lint;
+pub mod merge;
+pub mod pe;
+pub mod repair;
+pub mod replace;
+pub mod shortcuts;
+pub mod sync;
+pub mod table;
+pub mod template;
+pub mod vdf;
@@ -0,0 +1,555 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use crate::shortcuts::{Shortcut, ShortcutProp, Shortcuts};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// What a finding is about. The code is stable, scripts may rely on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    EmptyName,
+    ExeMissing,
+    ExeNotExecutable,
+    StartDirEmpty,
+    StartDirMissing,
+    IconMissing,
+    Quoting,
+    FlatpakMissing,
+    AppIdMismatch,
+    DuplicateAppId,
+    DuplicateName,
+    DuplicateTag,
+}
+
+impl Check {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Check::EmptyName => "empty-name",
+            Check::ExeMissing => "exe-missing",
+            Check::ExeNotExecutable => "exe-not-executable",
+            Check::StartDirEmpty => "start-dir-empty",
+            Check::StartDirMissing => "start-dir-missing",
+            Check::IconMissing => "icon-missing",
+            Check::Quoting => "quoting",
+            Check::FlatpakMissing => "flatpak-missing",
+            Check::AppIdMismatch => "app-id-mismatch",
+            Check::DuplicateAppId => "duplicate-app-id",
+            Check::DuplicateName => "duplicate-name",
+            Check::DuplicateTag => "duplicate-tag",
+        }
+    }
+
+    /// Errors are entries Steam cannot launch or tell apart, warnings the
+    /// ones that work but are likely wrong.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Check::EmptyName
+            | Check::ExeMissing
+            | Check::FlatpakMissing
+            | Check::DuplicateAppId => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+pub struct Finding {
+    pub index: u32,
+    pub app_name: String,
+    pub check: Check,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn severity(&self) -> Severity {
+        self.check.severity()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "severity": self.severity().as_str(),
+            "index": self.index,
+            "app_name": self.app_name,
+            "check": self.check.as_str(),
+            "message": self.message,
+        })
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<7} [{}] {:?} {}: {}",
+            self.severity().as_str(),
+            self.index,
+            self.app_name,
+            self.check.as_str(),
+            self.message
+        )
+    }
+}
+
+/// Path held by an `exe` or `start_dir` value and whether it is quoted.
+/// Steam writes both quoted (`"C:\Games\My Game\game.exe"`); anything after
+/// the closing quote is an argument. Returns `None` for an opening quote
+/// that is never closed.
+pub fn split_path(value: &str) -> Option<(&str, bool)> {
+    let value = value.trim();
+    match value.strip_prefix('"') {
+        Some(rest) => rest.find('"').map(|end| (&rest[..end], true)),
+        None => Some((value, false)),
+    }
+}
+
+fn text(sc: &Shortcut, key: &str) -> String {
+    sc.props
+        .get(key)
+        .map(|p| p.text().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Where `exe` points to. A bare command name (`flatpak`) is looked up in
+/// `PATH`, like the shell Steam launches it with does.
+pub fn resolve_exe(path: &str) -> PathBuf {
+    if !path.contains(std::path::MAIN_SEPARATOR) && !path.contains('/') {
+        if let Some(found) = std::env::var_os("PATH").and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(path))
+                .find(|p| p.is_file())
+        }) {
+            return found;
+        }
+    }
+    PathBuf::from(path)
+}
+
+/// Folders where flatpak installs applications, system wide and per user.
+fn flatpak_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from("/var/lib/flatpak/app")];
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(Path::new(&home).join(".local/share/flatpak/app"));
+    }
+    dirs
+}
+
+/// Check every entry of `scs`, in index order. Duplicates are reported on
+/// the entries after the first one.
+pub fn lint(scs: &Shortcuts) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut names: HashMap<String, u32> = HashMap::new();
+    let mut app_ids: HashMap<u32, u32> = HashMap::new();
+
+    for sc in scs.iter() {
+        let index = sc.prop_to_u32("index").unwrap_or_default();
+        let app_name = text(sc, "app_name");
+        let mut report = |check: Check, message: String| {
+            findings.push(Finding {
+                index,
+                app_name: app_name.clone(),
+                check,
+                message,
+            })
+        };
+
+        if app_name.trim().is_empty() {
+            report(Check::EmptyName, "app_name is empty".to_owned());
+        }
+
+        let exe = text(sc, "exe");
+        let start_dir = text(sc, "start_dir");
+        let exe_path = split_path(&exe);
+        let start_dir_path = split_path(&start_dir);
+        match exe_path {
+            Some(("", _)) => report(Check::ExeMissing, "exe is empty".to_owned()),
+            Some((path, _)) if !resolve_exe(path).exists() => {
+                report(Check::ExeMissing, format!("{:?} does not exist", path))
+            }
+            Some((path, _)) if !is_executable(&resolve_exe(path)) => report(
+                Check::ExeNotExecutable,
+                format!("{:?} is not an executable file", path),
+            ),
+            _ => (),
+        }
+        match start_dir_path {
+            Some(("", _)) => report(Check::StartDirEmpty, "start_dir is empty".to_owned()),
+            Some((path, _)) if !Path::new(path).is_dir() => report(
+                Check::StartDirMissing,
+                format!("{:?} is not an existing folder", path),
+            ),
+            _ => (),
+        }
+
+        let icon = text(sc, "icon");
+        if let Some((path, _)) = split_path(&icon) {
+            if !path.is_empty() && !Path::new(path).is_file() {
+                report(Check::IconMissing, format!("{:?} does not exist", path));
+            }
+        }
+
+        for (key, value, path) in [
+            ("exe", &exe, exe_path),
+            ("start_dir", &start_dir, start_dir_path),
+        ] {
+            match path {
+                None => report(Check::Quoting, format!("{} has an unclosed quote", key)),
+                Some((path, false)) if path.contains(' ') => report(
+                    Check::Quoting,
+                    format!("{} {:?} has spaces but is not quoted", key, value.trim()),
+                ),
+                _ => (),
+            }
+        }
+        if let (Some((a, a_quoted)), Some((b, b_quoted))) = (exe_path, start_dir_path) {
+            if !a.is_empty() && !b.is_empty() && a_quoted != b_quoted {
+                report(
+                    Check::Quoting,
+                    format!(
+                        "exe is {}quoted but start_dir is {}quoted",
+                        if a_quoted { "" } else { "not " },
+                        if b_quoted { "" } else { "not " }
+                    ),
+                );
+            }
+        }
+
+        let flatpak = text(sc, "flatpak_app_id");
+        let flatpak = flatpak.trim();
+        if !flatpak.is_empty() && !flatpak_dirs().iter().any(|d| d.join(flatpak).is_dir()) {
+            report(
+                Check::FlatpakMissing,
+                format!("flatpak {:?} is not installed", flatpak),
+            );
+        }
+
+        // 0 is what is written for a missing app_id: Steam computes it. A
+        // different stored one is only a warning, Steam keeps the id it gave
+        // an entry even when its exe or name change.
+        let computed = sc.compute_app_id();
+        let app_id = match sc.prop_to_u32("app_id") {
+            None | Some(0) => computed,
+            Some(app_id) => app_id,
+        };
+        if app_id != computed {
+            report(
+                Check::AppIdMismatch,
+                format!(
+                    "app_id is {} but exe and app_name give {}",
+                    app_id, computed
+                ),
+            );
+        }
+        if let Some(first) = app_ids.get(&app_id) {
+            report(
+                Check::DuplicateAppId,
+                format!("app_id {} is also used by entry [{}]", app_id, first),
+            );
+        } else {
+            app_ids.insert(app_id, index);
+        }
+
+        if !app_name.trim().is_empty() {
+            if let Some(first) = names.get(&app_name) {
+                report(
+                    Check::DuplicateName,
+                    format!("entry [{}] has the same name", first),
+                );
+            } else {
+                names.insert(app_name.clone(), index);
+            }
+        }
+
+        if let Some(ShortcutProp::Strings(tags)) = sc.props.get("tags") {
+            let mut seen = vec![];
+            for tag in tags {
+                if seen.contains(&tag) {
+                    report(Check::DuplicateTag, format!("tag {:?} is repeated", tag));
+                } else {
+                    seen.push(tag);
+                }
+            }
+        }
+    }
+    findings
+}
+
+/// Text of a string prop, `None` for one holding raw bytes: those are kept
+/// as they are rather than rewritten lossily.
+fn editable(sc: &Shortcut, key: &str) -> Option<String> {
+    match sc.props.get(key) {
+        Some(ShortcutProp::String(s)) => Some(s.clone()),
+        Some(ShortcutProp::Bytes(_)) => None,
+        _ => Some(String::new()),
+    }
+}
+
+fn quote(path: &str) -> String {
+    format!("\"{}\"", path)
+}
+
+/// A path with spaces that is not quoted yet.
+fn needs_quotes(path: &str) -> bool {
+    path.contains(' ') && !path.starts_with('"')
+}
+
+/// Fix what can be fixed without guessing, on every entry of `scs`:
+/// - an empty start_dir is set to the folder of the exe, quoted like the exe;
+/// - exe and start_dir with spaces are quoted like Steam does. An exe that
+///   is not an existing file is left alone, as the spaces may separate
+///   arguments;
+/// - repeated tags are dropped.
+///
+/// A stored app_id that differs from the one computed from the fixed exe and
+/// app_name is only recomputed with `recompute_app_ids`: Steam keys the
+/// custom artwork of an entry by its app_id, which is lost when it changes.
+pub fn fix(scs: &mut Shortcuts, recompute_app_ids: bool) {
+    let indices: Vec<u32> = scs
+        .iter()
+        .filter_map(|sc| sc.prop_to_u32("index"))
+        .collect();
+    for index in indices {
+        if let Some(sc) = scs.at_mut(&index) {
+            fix_entry(sc);
+            if recompute_app_ids {
+                recompute_app_id(sc);
+            }
+        }
+    }
+}
+
+fn fix_entry(sc: &mut Shortcut) {
+    if let (Some(exe), Some(start_dir)) = (editable(sc, "exe"), editable(sc, "start_dir")) {
+        let mut new_start_dir = start_dir.trim().to_owned();
+        let mut new_exe = exe.trim().to_owned();
+
+        if new_start_dir.is_empty() {
+            if let Some((path, quoted)) = split_path(&exe).filter(|(p, _)| !p.is_empty()) {
+                if let Some(dir) = resolve_exe(path)
+                    .parent()
+                    .filter(|d| !d.as_os_str().is_empty())
+                {
+                    let dir = dir.to_string_lossy();
+                    // Next to a quoted exe, Steam writes `"/path/to/dir/"`.
+                    new_start_dir = if quoted {
+                        quote(&format!("{}/", dir.trim_end_matches('/')))
+                    } else {
+                        dir.into_owned()
+                    };
+                }
+            }
+        }
+        if needs_quotes(&new_exe) && resolve_exe(&new_exe).is_file() {
+            new_exe = quote(&new_exe);
+        }
+        if needs_quotes(&new_start_dir) {
+            new_start_dir = quote(&new_start_dir);
+        }
+
+        if new_exe != exe {
+            sc.props
+                .insert("exe".to_owned(), ShortcutProp::String(new_exe));
+        }
+        if new_start_dir != start_dir {
+            sc.props
+                .insert("start_dir".to_owned(), ShortcutProp::String(new_start_dir));
+        }
+    }
+
+    if let Some(ShortcutProp::Strings(tags)) = sc.props.get_mut("tags") {
+        let mut seen = vec![];
+        tags.retain(|tag| {
+            if seen.contains(tag) {
+                false
+            } else {
+                seen.push(tag.clone());
+                true
+            }
+        });
+    }
+}
+
+/// Replace a stored app_id that is not the computed one. 0 is left alone,
+/// Steam computes it.
+fn recompute_app_id(sc: &mut Shortcut) {
+    let computed = sc.compute_app_id();
+    if matches!(sc.prop_to_u32("app_id"), Some(id) if id != 0 && id != computed) {
+        sc.props
+            .insert("app_id".to_owned(), ShortcutProp::UInt32(computed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(bytes: &[u8]) -> Shortcuts {
+        Shortcuts::parse(bytes, &mut 0).expect("example file")
+    }
+
+    /// Steam keeps the app id of an entry when its exe or name change, so
+    /// real files have mismatches: they must not fail the lint.
+    #[test]
+    fn example_files_have_no_app_id_errors() {
+        for bytes in [
+            &include_bytes!("../../examples/shortcuts.vdf")[..],
+            include_bytes!("../../examples/shortcuts_1.vdf"),
+        ] {
+            let findings = lint(&example(bytes));
+            assert!(
+                !findings.iter().any(|f| matches!(
+                    f.check,
+                    Check::DuplicateAppId | Check::AppIdMismatch
+                ) && f.severity() == Severity::Error),
+                "{}",
+                findings
+                    .iter()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+    }
+
+    #[test]
+    fn fix_keeps_app_ids_and_paths_without_spaces() {
+        let bytes = include_bytes!("../../examples/shortcuts_1.vdf");
+        let scs = example(bytes);
+        let mut fixed = scs.clone();
+        fix(&mut fixed, false);
+        for sc in scs.iter() {
+            let index = sc.prop_to_u32("index").unwrap();
+            let after = fixed.at(&index).unwrap();
+            assert_eq!(after.props.get("app_id"), sc.props.get("app_id"));
+            for key in ["exe", "start_dir"] {
+                let before = text(sc, key);
+                if !before.contains(' ') && !before.trim().is_empty() {
+                    assert_eq!(text(after, key), before, "[{}] {}", index, key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fix_quotes_paths_with_spaces_once() {
+        let mut sc = Shortcut::empty()
+            .with_string("exe", "/usr/bin/env")
+            .with_string("start_dir", "/home/me/My Games")
+            .with_u32("app_id", 7);
+
+        fix_entry(&mut sc);
+        assert_eq!(text(&sc, "exe"), "/usr/bin/env");
+        assert_eq!(text(&sc, "start_dir"), "\"/home/me/My Games\"");
+        assert_eq!(sc.prop_to_u32("app_id"), Some(7));
+
+        let once = sc.props.clone();
+        fix_entry(&mut sc);
+        assert_eq!(sc.props, once);
+    }
+
+    #[test]
+    fn filled_start_dir_is_quoted_like_the_exe() {
+        let mut scs = example(include_bytes!("../tests/corpus/minimal.vdf"));
+        fix(&mut scs, false);
+        let sc = scs.at(&0).unwrap();
+        assert_eq!(text(sc, "start_dir"), "\"/usr/bin/\"");
+        let quoting: Vec<String> = lint(&scs)
+            .iter()
+            .filter(|f| f.check == Check::Quoting)
+            .map(|f| f.to_string())
+            .collect();
+        assert!(quoting.is_empty(), "{:?}", quoting);
+
+        let mut sc = Shortcut::empty().with_string("exe", "/usr/bin/true");
+        fix_entry(&mut sc);
+        assert_eq!(text(&sc, "start_dir"), "/usr/bin");
+    }
+
+    #[test]
+    fn app_ids_are_only_recomputed_on_request() {
+        let entry = Shortcut::empty()
+            .with_string("app_name", "A")
+            .with_string("exe", "\"/usr/bin/true\"")
+            .with_string("start_dir", "\"/usr/bin/\"");
+        let computed = entry.compute_app_id();
+        let scs = Shortcuts::from_entries([
+            entry.clone().with_u32("app_id", 7),
+            entry.with_u32("app_id", 0),
+        ]);
+
+        let mut kept = scs.clone();
+        fix(&mut kept, false);
+        assert_eq!(kept.at(&0).unwrap().prop_to_u32("app_id"), Some(7));
+
+        let mut recomputed = scs;
+        fix(&mut recomputed, true);
+        assert_eq!(
+            recomputed.at(&0).unwrap().prop_to_u32("app_id"),
+            Some(computed)
+        );
+        assert_eq!(recomputed.at(&1).unwrap().prop_to_u32("app_id"), Some(0));
+    }
+
+    #[test]
+    fn stored_app_ids_other_than_the_computed_one_are_warned_about() {
+        let entry = Shortcut::empty()
+            .with_string("app_name", "A")
+            .with_string("exe", "\"/usr/bin/true\"");
+        let computed = entry.compute_app_id();
+        let scs = Shortcuts::from_entries([
+            entry.clone().with_u32("app_id", computed ^ 1),
+            entry.clone().with_u32("app_id", computed),
+            entry.with_string("app_name", "B").with_u32("app_id", 0),
+        ]);
+        let mismatches: Vec<Finding> = lint(&scs)
+            .into_iter()
+            .filter(|f| f.check == Check::AppIdMismatch)
+            .collect();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].index, 0);
+        assert_eq!(mismatches[0].severity(), Severity::Warning);
+        assert_eq!(
+            mismatches[0].message,
+            format!(
+                "app_id is {} but exe and app_name give {}",
+                computed ^ 1,
+                computed
+            )
+        );
+    }
+}
@@ -8,19 +8,23 @@
  * @author: Magius(CHE) - magiusche@magius.it
  */
 
-use chrono::{DateTime, NaiveDateTime, Utc};
 use clap::{Parser, Subcommand, ValueEnum};
-use iter_tools::Itertools;
 use std::{
+    collections::HashSet,
     fmt::Display,
     fs::File,
-    io::{BufReader, Read},
-    iter::once,
+    io::{BufReader, BufWriter, Read, Write},
     path::Path,
 };
 
-mod shortcuts;
+use steam_shortcuts_editor::{
+    columns, dedupe, desktop, diff, formats, icons, inspect, lint, merge, pe, repair, replace, shortcuts, sync, table, template, vdf,
+};
+use columns::{Column, SortKey};
+use formats::RecordsFormat;
 use shortcuts::{ShortcutProp, Shortcuts, SHORTCUT_PROP_INFO};
+use sync::Tracking;
+use template::Template;
 
 /// VDF Shortcuts Editor for Steam Client
 #[derive(Parser, Debug)]
@@ -42,13 +46,41 @@ enum Commands {
         separator: String,
 
         #[arg(long)]
-        /// Export list in JSON format. This will ignore "--separator", "--keys", "--last_play_time_*".
+        /// Export list in JSON format. Same as "--output json". This will ignore "--separator", "--keys", "--last_play_time_*".
         json: bool,
 
+        #[arg(long, ignore_case = true)]
+        /// Output format. Defaults to "table" when any column uses the "padded" format, "plain" otherwise.
+        output: Option<ListOutput>,
+
+        #[arg(long, default_value = ";")]
+        /// Separator used to join tags in a single "csv" or "tsv" cell
+        tags_delimiter: String,
+
+        #[arg(long, value_names(&["template"]), conflicts_with_all(&["json", "output"]))]
+        /// Print each entry using a template, e.g. '{app_id}\t{app_name|upper}\t{last_play_time|date:%F}'. Fields are column names; filters: upper, lower, trim, quote, shell, json, date[:fmt], utcdate[:fmt], default:text.
+        format: Option<String>,
+
+        #[arg(long)]
+        /// Decode strings that are not valid UTF-8 with this legacy encoding (e.g. "cp1252", "latin1", "shift_jis") instead of replacing invalid bytes. Display only.
+        encoding: Option<String>,
+
+        #[arg(long)]
+        /// Maximum width of the "table" output. Defaults to the terminal width, 0 disables truncation.
+        width: Option<usize>,
+
         #[arg(long)]
         /// Show key for each value in table output
         keys: bool,
 
+        #[arg(long, value_names(&["field[:desc]"]), value_delimiter = ',')]
+        /// Sort entries by field (any column name, optionally suffixed by ":asc" or ":desc"). Repeat or separate with commas to sort by multiple keys.
+        sort: Vec<String>,
+
+        #[arg(long)]
+        /// Show only the first N entries (applied after sorting)
+        limit: Option<usize>,
+
         #[arg(value_names(&["format"]),long,ignore_case = true, default_value_t = ListColumnsModes::None)]
         /// Shows Index with specified format
         index: ListColumnsModes,
@@ -136,6 +168,10 @@ enum Commands {
         #[arg(value_names(&["format"]),long, ignore_case = true, default_value_t = ListColumnsModes::None)]
         /// Override all columns format with the specified one
         all: ListColumnsModes,
+
+        #[arg(long, value_names(&["field"]), value_delimiter = ',')]
+        /// Comma separated columns to show, in the given order (e.g. "app_id,app_name,exe"). Any shortcut field is accepted, including keys unknown to this tool. Overrides the per-column flags.
+        columns: Vec<String>,
     },
     /// Update entries structure recreating .vdf shortcuts file
     Edit {
@@ -143,9 +179,25 @@ enum Commands {
         shortcuts_path: Option<String>,
 
         /// Path to json contains the entries. It will ignore --idx, --key, --val. If <SHORTCUTS_PATH> not exists, --json-path will be required.
-        #[arg(long)]
+        #[arg(long, conflicts_with_all(&["csv_path", "ndjson_path", "from"]))]
         json_path: Option<String>,
 
+        /// Path to a CSV file (or TSV when the extension is ".tsv") with a header row, used like --json-path.
+        #[arg(long, conflicts_with_all(&["json_path", "ndjson_path", "from"]))]
+        csv_path: Option<String>,
+
+        /// Path to a newline delimited JSON file (one object per line), used like --json-path.
+        #[arg(long, conflicts_with_all(&["json_path", "csv_path", "from"]))]
+        ndjson_path: Option<String>,
+
+        /// Path to entries in any supported format, detected by extension: .json, .yaml/.yml, .toml, .csv, .tsv, .ndjson/.jsonl. Used like --json-path.
+        #[arg(long, conflicts_with_all(&["json_path", "csv_path", "ndjson_path"]))]
+        from: Option<String>,
+
+        /// Separator of the tags inside a single --csv-path cell
+        #[arg(long, default_value = ";")]
+        tags_delimiter: String,
+
         /// Index of the entry to operate on (requires --key and --val) if the entry does not exist idx will be ignored and a new one will be created to the end of the list.
         #[arg(long)]
         idx: Option<u32>,
@@ -165,6 +217,312 @@ enum Commands {
         /// Overwrite destination (--out) if exists.
         #[arg(long)]
         force: bool,
+
+        /// Do not write anything: print the changes that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Bring shortcuts in line with a manifest: add missing entries, update changed ones and remove the ones it manages that are gone from the manifest. Entries added by hand are never touched.
+    Sync {
+        /// Path to input (and output) file "shortcuts.vdf". It will be created if missing.
+        shortcuts_path: String,
+
+        /// Manifest with the desired entries (.json, .yaml/.yml, .toml, .csv, .tsv, .ndjson). Entries need "app_name" and "exe", "app_id" is computed when missing.
+        #[arg(long)]
+        manifest: String,
+
+        /// Tag added to the entries managed by sync
+        #[arg(long, default_value = "steam-shortcuts-editor")]
+        marker: String,
+
+        /// Track the managed entries by app_id in this file instead of tagging them with --marker
+        #[arg(long)]
+        state: Option<String>,
+
+        /// Separator of the tags inside a single csv/tsv manifest cell
+        #[arg(long, default_value = ";")]
+        tags_delimiter: String,
+
+        /// Output file destination for generated vdf. Defaults to <SHORTCUTS_PATH>.
+        #[arg(long)]
+        out: Option<String>,
+
+        /// Overwrite destination if exists.
+        #[arg(long)]
+        force: bool,
+
+        /// Do not write anything: print the changes that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Compare two shortcuts files. Entries are matched by app id, then by name and exe.
+    Diff {
+        /// Path to the old "shortcuts.vdf"
+        old_path: String,
+
+        /// Path to the new "shortcuts.vdf"
+        new_path: String,
+
+        #[arg(long)]
+        /// Print the changes as a JSON array
+        json: bool,
+    },
+    /// Combine several shortcuts files into one, merging the entries found in more than one of them.
+    Merge {
+        /// Paths to the "shortcuts.vdf" files to merge. On conflicts, the first one is "ours".
+        #[arg(required = true, num_args = 2..)]
+        inputs: Vec<String>,
+
+        /// Field identifying the same shortcut in different files
+        #[arg(long, ignore_case = true, default_value = "app-id")]
+        by: MergeBy,
+
+        /// How to resolve a field that differs between two entries. Tags are always joined.
+        #[arg(long, ignore_case = true, default_value = "ours")]
+        policy: MergePolicy,
+
+        /// Output file destination for generated vdf.
+        #[arg(long)]
+        out: String,
+
+        /// Overwrite destination if exists.
+        #[arg(long)]
+        force: bool,
+
+        /// Do not write anything: print the changes that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Convert any binary VDF to JSON or text KeyValues and back, keeping key order, case and value types.
+    Convert {
+        /// Input file: binary VDF, text KeyValues or JSON (detected from its content)
+        input: String,
+
+        /// Output file, "-" for the standard output. Its format comes from the extension (.json, .vdf binary, .txt/.acf text) unless --to is given.
+        output: String,
+
+        /// Output format
+        #[arg(long, ignore_case = true)]
+        to: Option<ConvertFormat>,
+
+        /// Overwrite destination if exists.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the raw structure of a binary vdf file with byte offsets, type bytes and value bytes, showing where parsing stops.
+    Inspect {
+        /// Path to "shortcuts.vdf" (or any binary vdf)
+        shortcuts_path: String,
+    },
+    /// Recover every complete entry of a truncated or corrupted shortcuts file and write a valid file with them.
+    Repair {
+        /// Path to the damaged "shortcuts.vdf"
+        shortcuts_path: String,
+
+        /// Output file destination for the repaired vdf. Defaults to <SHORTCUTS_PATH>.
+        #[arg(long)]
+        out: Option<String>,
+
+        /// Overwrite destination if exists.
+        #[arg(long)]
+        force: bool,
+
+        /// Do not write anything: print the changes that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check every entry for missing files, bad quoting, stale app ids and duplicates. Exits with code 2 when an error is found.
+    Lint {
+        /// Path to "shortcuts.vdf"
+        shortcuts_path: String,
+
+        #[arg(long)]
+        /// Print the findings as a JSON array
+        json: bool,
+
+        #[arg(long, conflicts_with = "json")]
+        /// Quote exe and start_dir with spaces, fill empty start_dir and drop repeated tags, then write the result
+        fix: bool,
+
+        #[arg(long, requires = "fix")]
+        /// Also replace app ids that differ from the one computed from exe and app_name. Steam keys custom artwork by app id, so it is lost for the changed entries
+        recompute_app_ids: bool,
+
+        /// Output file destination for the fixed vdf. Defaults to <SHORTCUTS_PATH>.
+        #[arg(long, requires = "fix")]
+        out: Option<String>,
+
+        /// Overwrite destination if exists.
+        #[arg(long, requires = "fix")]
+        force: bool,
+
+        /// Do not write anything: print the changes that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long, requires = "fix")]
+        dry_run: bool,
+    },
+    /// Find entries added more than once and keep one of each group, joining the tags of the others into it.
+    Dedupe {
+        /// Path to "shortcuts.vdf"
+        shortcuts_path: String,
+
+        /// What makes two entries duplicates
+        #[arg(long, ignore_case = true, default_value = "app-id")]
+        by: DedupeBy,
+
+        /// Which entry of each group is kept
+        #[arg(long, ignore_case = true, default_value = "newest")]
+        keep: DedupeKeep,
+
+        /// Output file destination for the deduplicated vdf. Defaults to <SHORTCUTS_PATH>.
+        #[arg(long)]
+        out: Option<String>,
+
+        /// Overwrite destination if exists.
+        #[arg(long)]
+        force: bool,
+
+        /// Do not write anything: print the changes that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Find and replace text in string fields, e.g. the paths of a games folder mounted somewhere else.
+    Replace {
+        /// Path to "shortcuts.vdf"
+        shortcuts_path: String,
+
+        /// Text to look for, a regular expression with --regex
+        pattern: String,
+
+        /// Replacement text. With --regex, $1 or ${name} insert the captured groups.
+        replacement: String,
+
+        /// Read <PATTERN> as a regular expression
+        #[arg(long)]
+        regex: bool,
+
+        /// Comma separated fields to change
+        #[arg(long, value_delimiter = ',', default_value = "exe,start_dir,icon,shortcut_path")]
+        fields: Vec<String>,
+
+        /// Comma separated indices of the entries to change. Defaults to every entry.
+        #[arg(long, value_delimiter = ',')]
+        idx: Vec<u32>,
+
+        /// Refuse to write if a changed exe, start_dir, icon or shortcut_path does not exist.
+        #[arg(long)]
+        verify: bool,
+
+        /// Output file destination for the changed vdf. Defaults to <SHORTCUTS_PATH>.
+        #[arg(long)]
+        out: Option<String>,
+
+        /// Overwrite destination if exists.
+        #[arg(long)]
+        force: bool,
+
+        /// Do not write anything: print the changes that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Add a shortcut for each freedesktop ".desktop" launcher given, or found in the given folders.
+    ImportDesktop {
+        /// Path to "shortcuts.vdf"
+        shortcuts_path: String,
+
+        /// ".desktop" files, or folders whose ".desktop" files are imported unless hidden (e.g. /usr/share/applications)
+        #[arg(required = true)]
+        desktop_paths: Vec<String>,
+
+        /// Output file destination for generated vdf. Defaults to <SHORTCUTS_PATH>.
+        #[arg(long)]
+        out: Option<String>,
+
+        /// Overwrite destination if exists.
+        #[arg(long)]
+        force: bool,
+
+        /// Do not write anything: print the changes that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write a freedesktop ".desktop" launcher for each entry, so they appear in the desktop menus.
+    ExportDesktop {
+        /// Path to "shortcuts.vdf"
+        shortcuts_path: String,
+
+        /// Folder of the launchers. Defaults to $XDG_DATA_HOME/applications (~/.local/share/applications).
+        #[arg(long)]
+        out_dir: Option<String>,
+
+        /// Comma separated indices of the entries to export. Defaults to every entry.
+        #[arg(long, value_delimiter = ',')]
+        idx: Vec<u32>,
+
+        /// What the launchers run
+        #[arg(long, ignore_case = true, default_value = "steam")]
+        launch: DesktopLaunch,
+
+        /// Overwrite launchers that already exist.
+        #[arg(long)]
+        force: bool,
+
+        /// Do not write anything: print the launchers that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Set the icon of entries without an icon file, looking up their icon name, launcher or flatpak id in the icon themes.
+    ResolveIcons {
+        /// Path to "shortcuts.vdf"
+        shortcuts_path: String,
+
+        /// Icon theme looked into first. Defaults to the one of the desktop, then hicolor.
+        #[arg(long)]
+        theme: Option<String>,
+
+        /// Preferred icon size in pixels
+        #[arg(long, default_value_t = icons::DEFAULT_SIZE)]
+        size: u32,
+
+        /// Comma separated indices of the entries to change. Defaults to every entry.
+        #[arg(long, value_delimiter = ',')]
+        idx: Vec<u32>,
+
+        /// Output file destination for the changed vdf. Defaults to <SHORTCUTS_PATH>.
+        #[arg(long)]
+        out: Option<String>,
+
+        /// Overwrite destination if exists.
+        #[arg(long)]
+        force: bool,
+
+        /// Do not write anything: print the changes that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Set the icon of Windows games without an icon file to the icon embedded in their exe, saved in a cache folder.
+    ExtractIcons {
+        /// Path to "shortcuts.vdf"
+        shortcuts_path: String,
+
+        /// Folder of the extracted icons. Defaults to $XDG_CACHE_HOME/steam-shortcuts-editor/icons (~/.cache/...).
+        #[arg(long)]
+        cache_dir: Option<String>,
+
+        /// Comma separated indices of the entries to change. Defaults to every entry.
+        #[arg(long, value_delimiter = ',')]
+        idx: Vec<u32>,
+
+        /// Output file destination for the changed vdf. Defaults to <SHORTCUTS_PATH>.
+        #[arg(long)]
+        out: Option<String>,
+
+        /// Overwrite destination if exists.
+        #[arg(long)]
+        force: bool,
+
+        /// Do not write anything, icons included: print the changes that would be written and exit with code 3 if there are any, 0 otherwise.
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Print version information
     Version,
@@ -174,7 +532,7 @@ enum Commands {
 enum ListColumnsModes {
     None,
     Plain,
-    //Padded,
+    Padded,
 }
 impl Display for ListColumnsModes {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -182,9 +540,87 @@ impl Display for ListColumnsModes {
     }
 }
 
+#[derive(ValueEnum, Clone, Debug, Eq, PartialEq)]
+enum ListOutput {
+    /// Separator-joined values, one line per entry
+    Plain,
+    /// Aligned columns with a header row
+    Table,
+    /// JSON array of objects with every field
+    Json,
+    /// Comma separated values with a header row. Uses "--columns" or every field.
+    Csv,
+    /// Tab separated values with a header row. Uses "--columns" or every field.
+    Tsv,
+    /// One JSON object per line with every field
+    Ndjson,
+    /// YAML sequence of entries with every field
+    Yaml,
+    /// TOML "[[shortcuts]]" tables with every field
+    Toml,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ConvertFormat {
+    /// Binary VDF, as "shortcuts.vdf"
+    Binary,
+    /// Text KeyValues, non-string values carry a "// type" comment
+    Text,
+    /// JSON, non-string values other than int32 are {"$type": ..., "value": ...} objects
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum MergeBy {
+    /// Same app id, computed when missing or 0
+    AppId,
+    /// Same executable path
+    Exe,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum MergePolicy {
+    /// Keep the value of the file listed first
+    Ours,
+    /// Take the value of the file listed later
+    Theirs,
+    /// Take the value of the entry played last
+    Newest,
+    /// Ask for every conflict
+    Interactive,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum DedupeBy {
+    /// Same app id
+    AppId,
+    /// Same executable path and launch options
+    Exe,
+    /// Same name, ignoring case
+    Name,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum DedupeKeep {
+    /// The entry played last
+    Newest,
+    /// The entry with the lowest index
+    First,
+    /// The entry with the most tags
+    MostTags,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum DesktopLaunch {
+    /// Run the exe directly, with its launch options
+    Exe,
+    /// Ask Steam to run the shortcut (steam://rungameid/<id>)
+    Steam,
+}
+
 fn main() {
     match handle_commandline() {
-        Ok(_) => std::process::exit(exitcode::OK),
+        Ok(code) => std::process::exit(code),
         Err(e) => {
             match e {
                 Error::InvalidInputFile(m) => eprintln!("Error! Invalid input file: {}", m),
@@ -193,6 +629,7 @@ fn main() {
                     eprintln!("Error! Cannot convert string to uint32: {}", m),
                 
                 Error::IvalidStringsPassed(m) => eprintln!("Error! Cannot convert string to string array: {}", m),
+                Error::InvalidArgument(m) => eprintln!("Error! Invalid argument: {}", m),
             };
             eprintln!("Program aborted.");
             std::process::exit(1);
@@ -200,50 +637,87 @@ fn main() {
     }
 }
 
-fn handle_commandline() -> Result<(), Error> {
+fn handle_commandline() -> Result<i32, Error> {
     // https://blog.logrocket.com/command-line-argument-parsing-rust-using-clap/
     let args = Cli::parse();
 
-    match &args.command {
-        Commands::List { .. } => list_shortcuts(&args)?,
-        Commands::Version => println!(
-            "{} {} by {}, {}",
-            env!("CARGO_PKG_NAME"),
-            env!("CARGO_PKG_VERSION"),
-            env!("CARGO_PKG_AUTHORS"),
-            env!("CARGO_PKG_HOMEPAGE")
-        ),
+    Ok(match &args.command {
+        Commands::List { .. } => list_shortcuts(&args).map(|_| exitcode::OK)?,
+        Commands::Version => {
+            println!(
+                "{} {} by {}, {}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+                env!("CARGO_PKG_AUTHORS"),
+                env!("CARGO_PKG_HOMEPAGE")
+            );
+            exitcode::OK
+        }
         Commands::Edit { .. } => edit_shortcuts(&args)?,
-    };
-
-    Ok(())
+        Commands::Sync { .. } => sync_shortcuts(&args)?,
+        Commands::Diff { .. } => diff_shortcuts(&args).map(|_| exitcode::OK)?,
+        Commands::Merge { .. } => merge_shortcuts(&args)?,
+        Commands::Convert { .. } => convert_vdf(&args).map(|_| exitcode::OK)?,
+        Commands::Inspect { .. } => inspect_vdf(&args).map(|_| exitcode::OK)?,
+        Commands::Repair { .. } => repair_shortcuts(&args)?,
+        Commands::Lint { .. } => lint_shortcuts(&args)?,
+        Commands::Dedupe { .. } => dedupe_shortcuts(&args)?,
+        Commands::Replace { .. } => replace_in_shortcuts(&args)?,
+        Commands::ImportDesktop { .. } => import_desktop(&args)?,
+        Commands::ExportDesktop { .. } => export_desktop(&args)?,
+        Commands::ResolveIcons { .. } => resolve_icons(&args)?,
+        Commands::ExtractIcons { .. } => extract_icons(&args)?,
+    })
 }
 
-fn edit_shortcuts(args: &Cli) -> Result<(), Error> {
+fn edit_shortcuts(args: &Cli) -> Result<i32, Error> {
     if let Commands::Edit {
         shortcuts_path,
         out,
         json_path,
+        csv_path,
+        ndjson_path,
+        from,
+        tags_delimiter,
         key,
         val,
         idx,
         force,
+        dry_run,
         ..
     } = &args.command
     {
+        let records_input = if let Some(p) = from {
+            match RecordsFormat::from_extension(Path::new(p)) {
+                Some(format) => Some((p, format)),
+                None => {
+                    return Err(Error::InvalidInputFile(format!(
+                        "Cannot detect the format of \"{}\" from its extension.",
+                        p
+                    )))
+                }
+            }
+        } else if let Some(p) = json_path {
+            Some((p, RecordsFormat::Json))
+        } else if let Some(p) = csv_path {
+            Some((p, RecordsFormat::csv_from_extension(Path::new(p))))
+        } else {
+            ndjson_path.as_ref().map(|p| (p, RecordsFormat::Ndjson))
+        };
+
         if shortcuts_path.is_none() && out.is_none() {
             return Err(Error::InvalidInputFile(String::from(
                 "Missing required <SHORTCUTS_PATH> or --out. Check the usage.",
             )));
         }
-        if shortcuts_path.is_none() && json_path.is_none() {
+        if shortcuts_path.is_none() && records_input.is_none() {
             return Err(Error::InvalidInputFile(String::from(
-                "Missing required <SHORTCUTS_PATH> or --json-path. Check the usage.",
+                "Missing required <SHORTCUTS_PATH> or --json-path (--from, --csv-path, --ndjson-path). Check the usage.",
             )));
         }
-        if json_path.is_none() && (key.is_none() || val.is_none() || idx.is_none()) {
+        if records_input.is_none() && (key.is_none() || val.is_none() || idx.is_none()) {
             return Err(Error::InvalidInputFile(String::from(
-                "Missing required --json-path or --idx + --key + --val. Check the usage.",
+                "Missing required --json-path (--from, --csv-path, --ndjson-path) or --idx + --key + --val. Check the usage.",
             )));
         }
         let mut scs = if let Some(path) = shortcuts_path {
@@ -258,7 +732,14 @@ fn edit_shortcuts(args: &Cli) -> Result<(), Error> {
                 //let p = mkey.clone();
                 //sc.props.entry(String::from(mkey.clone().as_str()));
                 //let j = String::from(mkey.as_str());
-                match &sc.props[k] {
+                // Missing props of known keys, e.g. on a new entry, take the type of their default.
+                let current = sc.props.get(k).or_else(|| {
+                    SHORTCUT_PROP_INFO
+                        .iter()
+                        .find(|p| p.switchname == k)
+                        .map(|p| &p.type_default)
+                });
+                match current.unwrap_or_default() {
                     ShortcutProp::UInt32(_) => {
                         if let Ok(tou32) = v.parse::<u32>() {
                             *sc.props.entry(k.clone()).or_default() = ShortcutProp::UInt32(tou32);
@@ -270,7 +751,7 @@ fn edit_shortcuts(args: &Cli) -> Result<(), Error> {
                             )))
                         }
                     }
-                    ShortcutProp::String(_) => {
+                    ShortcutProp::String(_) | ShortcutProp::Bytes(_) => {
                         *sc.props.entry(k.clone()).or_default() = ShortcutProp::String(v.clone());
                         Ok(())
                     }
@@ -287,164 +768,1004 @@ fn edit_shortcuts(args: &Cli) -> Result<(), Error> {
                             )))
                         }
                     }
-                    ShortcutProp::None => panic!("Shortcut[{}][{}] contains a None propertry. This is not acceptable.", i, k),
+                    ShortcutProp::None => Err(Error::InvalidArgument(format!(
+                        "Shortcut[{}] has no property \"{}\". Known ones are: {}.",
+                        i,
+                        k,
+                        SHORTCUT_PROP_INFO.iter().map(|p| p.switchname).collect::<Vec<_>>().join(", ")
+                    ))),
                 }
             })?;
-        } else if let Some(jpath) = json_path {
+        } else if let Some((jpath, format)) = records_input {
             let jpathfile = Path::new(jpath);
             if !jpathfile.exists(){
-                return Err(Error::InvalidInputFile(format!("JSON Path is invalid. Missing file at {}",jpath)))
+                return Err(Error::InvalidInputFile(format!("{:?} Path is invalid. Missing file at {}",format,jpath)))
             }
 
             match File::open(jpathfile){
                 Ok(mut file) => {
                     let mut buf = String::new();
-                    if let Ok(_) = file.read_to_string(&mut buf) {
-                        match scs.update_from_json(&buf) {
+                    if file.read_to_string(&mut buf).is_ok() {
+                        match formats::read_records(&buf, format, tags_delimiter).and_then(|records| if format == RecordsFormat::Json { scs.update_from_values(&records) } else { scs.import_records(&records) }) {
                             Ok(_) => Ok(()),
-                            Err(err) => Err(Error::InvalidInputFile(format!("JSON Input file is invalid: {:?}",err)))
+                            Err(err) => Err(Error::InvalidInputFile(format!("{:?} Input file is invalid: {:?}",format,err)))
                         }
                     } else {
-                        Err(Error::InvalidInputFile(format!("Canno read from JSON Input file {}",jpath)))
+                        Err(Error::InvalidInputFile(format!("Canno read from {:?} Input file {}",format,jpath)))
                     }
                 },
-                Err(_) => todo!(),
+                Err(err) => Err(Error::InvalidInputFile(format!("Cannot open {:?} Input file {}. {:?}",format,jpath,err))),
             }?;
-
-            
-        }
-
-        let destination = Path::new( if let Some(p) = out { p } else { shortcuts_path.as_ref().unwrap() } );
-        
-        // Check if destination esists, is exists test force!
-        if destination.exists() && !force{
-            return Err(Error::InvalidOutputFile(format!("Shortcuts file already exists at: \"{}\". Use --force to overwire it.", destination.to_str().unwrap())))
         }
 
-        println!("Write to file: {}", destination.to_str().unwrap());
-        
-        match File::create(destination) {
-            Ok(mut file) =>  match scs.store_into(&mut file){
-                Ok(_) => Ok(()),
-                Err(err) => Err(Error::InvalidOutputFile(format!("Unable to create file {}. {:?}", destination.to_str().unwrap(),err))),
-            },
-            Err(err) => Err(Error::InvalidOutputFile(format!("Unable to create file {}. {:?}", destination.to_str().unwrap(),err)))
-        }?;
+        store_shortcuts(&scs, if let Some(p) = out { p } else { shortcuts_path.as_ref().unwrap() }, *force, *dry_run)
     } else {
         unreachable!();
     }
-    Ok(())
 }
 
-fn load_shortcuts(shortcuts_path: &str) -> Result<Shortcuts, Error> {
-    // Ensure shortcuts_vdf path
-    let path_joined = Path::new(&shortcuts_path).join("shortcuts.vdf");
-    let path_raw = Path::new(&shortcuts_path);
+/// Exit code of a `lint` that found at least one error.
+const EXIT_LINT_ERRORS: i32 = 2;
 
-    let shortcuts_vdf = match path_raw.file_name() {
-        Some(_) if path_raw.exists() && path_raw.is_file() => path_raw.clone(),
-        Some(_) if path_joined.exists() && path_joined.is_file() => Path::new(&path_joined),
-        _ => return Err(Error::InvalidInputFile(String::from(
-            "<SHORTCUTS_PATH> must be an existining file or folder contains .../<shortcuts>.vdf",
-        ))),
-    };
+/// Exit code of a `--dry-run` that would change the destination.
+const EXIT_PENDING_CHANGES: i32 = 3;
 
-    //println!("Analyze {:?}:", shortcuts_vdf);
+/// Write `scs` to `destination` (a file or a folder that will contain
+/// "shortcuts.vdf"), refusing to overwrite an existing file without `force`.
+/// With `dry_run` the changes are only printed. Returns the exit code.
+fn store_shortcuts(scs: &Shortcuts, destination: &str, force: bool, dry_run: bool) -> Result<i32, Error> {
+    let joined = Path::new(destination).join("shortcuts.vdf");
+    let destination = if Path::new(destination).is_dir() {
+        joined.as_path()
+    } else {
+        Path::new(destination)
+    };
 
-    let ret = File::open(shortcuts_vdf.as_os_str().to_str().unwrap());
-    if ret.is_err() {
-        return Err(Error::InvalidInputFile(String::from(format!(
-            "{:?} cannot be opened due to: {:?}",
-            shortcuts_vdf, ret
-        ))));
+    if dry_run {
+        return preview_shortcuts(scs, destination, force);
     }
 
-    let f = ret.unwrap();
-
-    let mut reader = BufReader::new(f);
-    let mut buffer = Vec::new();
-
-    // Read file into vector.
-    if reader.read_to_end(&mut buffer).unwrap_or(0) == 0 {
-        return Err(Error::InvalidInputFile(String::from(format!(
-            "{:?} cannot be read or is empty.",
-            shortcuts_vdf
-        ))));
+    // Check if destination esists, is exists test force!
+    if destination.exists() && !force{
+        return Err(Error::InvalidOutputFile(format!("Shortcuts file already exists at: \"{}\". Use --force to overwire it.", destination.to_str().unwrap())))
     }
 
-    let mut index = 0;
+    println!("Write to file: {}", destination.to_str().unwrap());
 
-    if let Some(scs) = Shortcuts::from(&buffer, &mut index) {
-        return Ok(scs);
+    match File::create(destination) {
+        Ok(file) =>  match scs.store_into(&mut BufWriter::new(file)){
+            Ok(_) => Ok(exitcode::OK),
+            Err(err) => Err(Error::InvalidOutputFile(format!("Unable to create file {}. {:?}", destination.to_str().unwrap(),err))),
+        },
+        Err(err) => Err(Error::InvalidOutputFile(format!("Unable to create file {}. {:?}", destination.to_str().unwrap(),err)))
     }
-    Err(Error::InvalidInputFile(format!(
-        "Error while loading file {}",
-        shortcuts_path
-    )))
 }
 
-macro_rules! format_column_output {
-    ($sc:expr,$keys:expr,$all:expr,$b:expr,$t:ty,$c:expr,$d:expr) => {
-        once(
-            match if $all != &ListColumnsModes::None {
-                $all
-            } else {
-                $b
-            } {
-                ListColumnsModes::Plain => {
-                    //println!("format_column_output {},{:?}",stringify!($b), $sc);
-                    let p = SHORTCUT_PROP_INFO.iter().find(|s|s.switchname == stringify!($b)).unwrap();
-                    //println!("found format_column_output {}, {:?}",stringify!($b),$sc.props.get($c));
-                    let val =
-                        TryInto::<$t>::try_into($sc.props.get($c).unwrap_or_default()).unwrap();
-                    Some(if *$keys {
-                        (p, format!(concat!(stringify!($b), " = ", $d), val))
-                    } else {
-                        (p,format!($d, val))
-                    })
-                }
-                _ => None,
-            },
-        )
+/// Print what writing `scs` to `destination` would change, compared to its
+/// current content (or to an empty file when it does not exist yet). The
+/// exit code tells whether the stored entries would differ: both lists are
+/// stored the same way and compared byte by byte, as the field changes
+/// printed ignore entries that only move to another index.
+fn preview_shortcuts(scs: &Shortcuts, destination: &Path, force: bool) -> Result<i32, Error> {
+    let stored = |scs: &Shortcuts| -> Result<Vec<u8>, Error> {
+        let mut bytes = vec![];
+        scs.store_into(&mut bytes).map_err(Error::InvalidOutputFile)?;
+        Ok(bytes)
     };
-}
-
-macro_rules! format_column_output_ex {
-    ($keys:expr,$all:expr,$b:expr,$prop:expr,$c:expr,$d:expr) => {
-        once(
-            match if $all != &ListColumnsModes::None {
-                $all
-            } else {
-                $b
-            } {
-                ListColumnsModes::Plain => {
-                    let p = SHORTCUT_PROP_INFO
-                        .iter()
-                        .find(|s| s.switchname == stringify!($prop))
-                        .unwrap();
-                    let val = $c;
-                    Some(if *$keys {
-                        (p, format!(concat!(stringify!($b), " = ", $d), val))
-                    } else {
-                        (p, format!($d, val))
-                    })
-                }
-                _ => None,
-            },
-        )
+    let current = if destination.exists() {
+        // A file that cannot be loaded (e.g. the one being repaired) is
+        // compared as if it was empty.
+        load_shortcuts(destination.to_str().unwrap()).map_err(|_| {
+            println!("Note: the current file cannot be loaded, every entry is shown as added.");
+        }).ok()
+    } else {
+        None
+    };
+    let unchanged = match &current {
+        Some(current) => stored(current)? == stored(scs)?,
+        None => false,
     };
+    let current = current.unwrap_or_else(Shortcuts::empty);
+    let changes = diff::diff(&current, scs);
+
+    println!("Dry run, nothing written to: {}", destination.to_str().unwrap());
+    if unchanged {
+        println!("No changes.");
+        return Ok(exitcode::OK);
+    }
+    for change in &changes {
+        println!("{}", change);
+    }
+    if changes.is_empty() {
+        println!("No field changes, but the file would be written: entries are renumbered or reordered, or the file is new.");
+    }
+    if destination.exists() && !force {
+        println!("Note: the file already exists, --force is needed to overwrite it.");
+    }
+    Ok(EXIT_PENDING_CHANGES)
 }
 
-fn list_shortcuts(args: &Cli) -> Result<(), Error> {
-    if let Commands::List {
+fn sync_shortcuts(args: &Cli) -> Result<i32, Error> {
+    if let Commands::Sync {
         shortcuts_path,
-        separator,
-        index,
-        app_id,
-        app_name,
-        exe,
-        icon,
-        allow_desktop_config,
+        manifest,
+        marker,
+        state,
+        tags_delimiter,
+        out,
+        force,
+        dry_run,
+    } = &args.command
+    {
+        let mut scs = if Path::new(shortcuts_path).exists() {
+            load_shortcuts(shortcuts_path)?
+        } else {
+            Shortcuts::empty()
+        };
+
+        let records = read_records_file(manifest, tags_delimiter)?;
+
+        let tracking = match state {
+            Some(path) => Tracking::State(read_sync_state(path)?),
+            None => Tracking::Marker(marker.clone()),
+        };
+
+        let report = sync::sync(&mut scs, &records, &tracking)
+            .map_err(|e| Error::InvalidInputFile(format!("Manifest {} is invalid: {}", manifest, e)))?;
+
+        for action in &report.actions {
+            println!("{}", action);
+        }
+
+        let code = if report.actions.iter().any(|a| a.is_change()) {
+            store_shortcuts(&scs, out.as_ref().unwrap_or(shortcuts_path), *force, *dry_run)?
+        } else {
+            println!("No changes.");
+            exitcode::OK
+        };
+        if *dry_run {
+            return Ok(code);
+        }
+
+        // The state is stored only once the shortcuts are written, so a failed
+        // write never leaves entries that sync would no longer recognize.
+        if let (Some(path), Tracking::State(previous)) = (state, &tracking) {
+            if report.managed_ids.len() != previous.len()
+                || report.managed_ids.iter().any(|id| !previous.contains(id))
+            {
+                write_sync_state(path, &report.managed_ids)?;
+            }
+        }
+        Ok(code)
+    } else {
+        unreachable!();
+    }
+}
+
+/// Read entries from a file in any format detected by its extension.
+fn read_records_file(path: &str, tags_delimiter: &str) -> Result<Vec<serde_json::Value>, Error> {
+    let format = RecordsFormat::from_extension(Path::new(path)).ok_or(Error::InvalidInputFile(
+        format!("Cannot detect the format of \"{}\" from its extension.", path),
+    ))?;
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| Error::InvalidInputFile(format!("Cannot read {}. {:?}", path, e)))?;
+    formats::read_records(&text, format, tags_delimiter)
+        .map_err(|e| Error::InvalidInputFile(format!("{:?} Input file is invalid: {}", format, e)))
+}
+
+/// Sync state file: `{"managed_app_ids": [...]}`. A missing file means no entry is managed yet.
+fn read_sync_state(path: &str) -> Result<HashSet<u32>, Error> {
+    if !Path::new(path).exists() {
+        return Ok(HashSet::new());
+    }
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| Error::InvalidInputFile(format!("Cannot read state {}. {:?}", path, e)))?;
+    serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|v| {
+            v.get("managed_app_ids")?
+                .as_array()?
+                .iter()
+                .map(|id| id.as_u64().and_then(|id| u32::try_from(id).ok()))
+                .collect::<Option<HashSet<u32>>>()
+        })
+        .ok_or(Error::InvalidInputFile(format!(
+            "State file {} is invalid. Expected {{\"managed_app_ids\": [...]}}.",
+            path
+        )))
+}
+
+fn write_sync_state(path: &str, ids: &[u32]) -> Result<(), Error> {
+    let mut root = serde_json::Map::new();
+    root.insert("managed_app_ids".to_owned(), serde_json::Value::from(ids.to_vec()));
+    std::fs::write(path, serde_json::Value::Object(root).to_string())
+        .map_err(|e| Error::InvalidOutputFile(format!("Unable to write state {}. {:?}", path, e)))
+}
+
+fn diff_shortcuts(args: &Cli) -> Result<(), Error> {
+    if let Commands::Diff {
+        old_path,
+        new_path,
+        json,
+    } = &args.command
+    {
+        let old = load_shortcuts(old_path)?;
+        let new = load_shortcuts(new_path)?;
+        let changes = diff::diff(&old, &new);
+
+        if *json {
+            println!(
+                "{}",
+                serde_json::Value::Array(changes.iter().map(|c| c.to_json()).collect())
+            );
+        } else if changes.is_empty() {
+            println!("No differences.");
+        } else {
+            for change in &changes {
+                println!("{}", change);
+            }
+        }
+    } else {
+        unreachable!();
+    }
+    Ok(())
+}
+
+fn merge_shortcuts(args: &Cli) -> Result<i32, Error> {
+    if let Commands::Merge {
+        inputs,
+        by,
+        policy,
+        out,
+        force,
+        dry_run,
+    } = &args.command
+    {
+        let inputs = inputs
+            .iter()
+            .map(|p| load_shortcuts(p))
+            .collect::<Result<Vec<Shortcuts>, Error>>()?;
+        let key = match by {
+            MergeBy::AppId => merge::MergeKey::AppId,
+            MergeBy::Exe => merge::MergeKey::Exe,
+        };
+        let mut ask = ask_conflict;
+        let mut policy = match policy {
+            MergePolicy::Ours => merge::Policy::Ours,
+            MergePolicy::Theirs => merge::Policy::Theirs,
+            MergePolicy::Newest => merge::Policy::Newest,
+            MergePolicy::Interactive => merge::Policy::Interactive(&mut ask),
+        };
+
+        let (merged, log) =
+            merge::merge(&inputs, key, &mut policy).map_err(Error::InvalidArgument)?;
+        for line in &log {
+            println!("{}", line);
+        }
+        println!(
+            "{} entries from {} files merged into {}.",
+            inputs.iter().map(|s| s.len()).sum::<usize>(),
+            inputs.len(),
+            merged.len()
+        );
+        store_shortcuts(&merged, out, *force, *dry_run)
+    } else {
+        unreachable!();
+    }
+}
+
+/// Prompt on the terminal which value of a merge conflict to keep.
+fn ask_conflict(conflict: &merge::Conflict) -> Result<merge::Side, String> {
+    merge::ask(conflict, &mut std::io::stdin().lock(), &mut std::io::stderr())
+}
+
+fn convert_vdf(args: &Cli) -> Result<(), Error> {
+    if let Commands::Convert {
+        input,
+        output,
+        to,
+        force,
+    } = &args.command
+    {
+        let format = match to {
+            Some(ConvertFormat::Binary) => vdf::VdfFormat::Binary,
+            Some(ConvertFormat::Text) => vdf::VdfFormat::Text,
+            Some(ConvertFormat::Json) => vdf::VdfFormat::Json,
+            None => match Path::new(output)
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_lowercase())
+                .as_deref()
+            {
+                Some("json") => vdf::VdfFormat::Json,
+                Some("vdf") => vdf::VdfFormat::Binary,
+                Some("txt") | Some("acf") => vdf::VdfFormat::Text,
+                _ => {
+                    return Err(Error::InvalidArgument(format!(
+                        "Cannot detect the output format of \"{}\". Use --to.",
+                        output
+                    )))
+                }
+            },
+        };
+
+        let buffer = std::fs::read(input)
+            .map_err(|e| Error::InvalidInputFile(format!("Cannot read {}. {:?}", input, e)))?;
+        let input_format = vdf::VdfFormat::sniff(&buffer);
+        let kv = vdf::KeyValues::read(&buffer, input_format).map_err(|e| {
+            Error::InvalidInputFile(format!("{} is not a valid {:?} file: {}", input, input_format, e))
+        })?;
+        let bytes = kv.write(format).map_err(Error::InvalidOutputFile)?;
+
+        if output == "-" {
+            std::io::stdout()
+                .write_all(&bytes)
+                .map_err(|e| Error::InvalidOutputFile(format!("{:?}", e)))?;
+        } else {
+            if Path::new(output).exists() && !force {
+                return Err(Error::InvalidOutputFile(format!(
+                    "File already exists at: \"{}\". Use --force to overwire it.",
+                    output
+                )));
+            }
+            std::fs::write(output, bytes).map_err(|e| {
+                Error::InvalidOutputFile(format!("Unable to create file {}. {:?}", output, e))
+            })?;
+        }
+    } else {
+        unreachable!();
+    }
+    Ok(())
+}
+
+fn inspect_vdf(args: &Cli) -> Result<(), Error> {
+    if let Commands::Inspect { shortcuts_path } = &args.command {
+        let joined = Path::new(shortcuts_path).join("shortcuts.vdf");
+        let path = if Path::new(shortcuts_path).is_dir() {
+            joined.as_path()
+        } else {
+            Path::new(shortcuts_path)
+        };
+        let buffer = std::fs::read(path).map_err(|e| {
+            Error::InvalidInputFile(format!("Cannot read {}. {:?}", path.display(), e))
+        })?;
+        inspect::inspect(&buffer).map_err(|p| {
+            Error::InvalidInputFile(format!(
+                "Parsing stopped at offset 0x{:x}: {}",
+                p.offset, p.message
+            ))
+        })?;
+    } else {
+        unreachable!();
+    }
+    Ok(())
+}
+
+fn lint_shortcuts(args: &Cli) -> Result<i32, Error> {
+    if let Commands::Lint {
+        shortcuts_path,
+        json,
+        fix,
+        recompute_app_ids,
+        out,
+        force,
+        dry_run,
+    } = &args.command
+    {
+        let scs = load_shortcuts(shortcuts_path)?;
+        if *fix {
+            let mut fixed = scs.clone();
+            lint::fix(&mut fixed, *recompute_app_ids);
+            let changes = diff::diff(&scs, &fixed);
+            if changes.is_empty() {
+                println!("Nothing to fix.");
+                return Ok(exitcode::OK);
+            }
+            if !*dry_run {
+                for change in &changes {
+                    println!("{}", change);
+                }
+            }
+            return store_shortcuts(
+                &fixed,
+                out.as_ref().unwrap_or(shortcuts_path),
+                *force,
+                *dry_run,
+            );
+        }
+        let findings = lint::lint(&scs);
+        let errors = findings
+            .iter()
+            .filter(|f| f.severity() == lint::Severity::Error)
+            .count();
+
+        if *json {
+            println!(
+                "{}",
+                serde_json::Value::Array(findings.iter().map(|f| f.to_json()).collect())
+            );
+        } else {
+            for finding in &findings {
+                println!("{}", finding);
+            }
+            if findings.is_empty() {
+                println!("No problems found in {} entries.", scs.len());
+            } else {
+                println!(
+                    "{} errors, {} warnings in {} entries.",
+                    errors,
+                    findings.len() - errors,
+                    scs.len()
+                );
+            }
+        }
+        Ok(if errors > 0 {
+            EXIT_LINT_ERRORS
+        } else {
+            exitcode::OK
+        })
+    } else {
+        unreachable!();
+    }
+}
+
+fn dedupe_shortcuts(args: &Cli) -> Result<i32, Error> {
+    if let Commands::Dedupe {
+        shortcuts_path,
+        by,
+        keep,
+        out,
+        force,
+        dry_run,
+    } = &args.command
+    {
+        let mut scs = load_shortcuts(shortcuts_path)?;
+        let key = match by {
+            DedupeBy::AppId => dedupe::DedupeKey::AppId,
+            DedupeBy::Exe => dedupe::DedupeKey::Exe,
+            DedupeBy::Name => dedupe::DedupeKey::Name,
+        };
+        let keep = match keep {
+            DedupeKeep::Newest => dedupe::Keep::Newest,
+            DedupeKeep::First => dedupe::Keep::First,
+            DedupeKeep::MostTags => dedupe::Keep::MostTags,
+        };
+
+        let groups = dedupe::groups(&scs, key, keep);
+        if groups.is_empty() {
+            println!("No duplicates found.");
+            return Ok(exitcode::OK);
+        }
+        for group in &groups {
+            println!("{}", group);
+        }
+        let before = scs.len();
+        dedupe::dedupe(&mut scs, &groups);
+        println!(
+            "{} duplicates removed from {} groups, {} entries left.",
+            before - scs.len(),
+            groups.len(),
+            scs.len()
+        );
+
+        store_shortcuts(
+            &scs,
+            out.as_ref().unwrap_or(shortcuts_path),
+            *force,
+            *dry_run,
+        )
+    } else {
+        unreachable!();
+    }
+}
+
+fn replace_in_shortcuts(args: &Cli) -> Result<i32, Error> {
+    if let Commands::Replace {
+        shortcuts_path,
+        pattern,
+        replacement,
+        regex,
+        fields,
+        idx,
+        verify,
+        out,
+        force,
+        dry_run,
+    } = &args.command
+    {
+        let mut scs = load_shortcuts(shortcuts_path)?;
+        replace::check_fields(fields).map_err(Error::InvalidArgument)?;
+        let replacer = replace::Replacer::new(pattern, replacement, *regex)
+            .map_err(|e| Error::InvalidArgument(format!("Invalid pattern. {}", e)))?;
+
+        let before = scs.clone();
+        let changed = replace::replace(&mut scs, idx, fields, &replacer);
+        if changed.is_empty() {
+            println!("No match.");
+            return Ok(exitcode::OK);
+        }
+        if !*dry_run {
+            for change in diff::diff(&before, &scs) {
+                println!("{}", change);
+            }
+        }
+        let entries: HashSet<u32> = changed.iter().map(|(i, _)| *i).collect();
+        println!("{} fields changed in {} entries.", changed.len(), entries.len());
+
+        if *verify {
+            let missing = replace::missing_paths(&scs, &changed);
+            if !missing.is_empty() {
+                for m in &missing {
+                    println!("{}", m);
+                }
+                return Err(Error::InvalidArgument(format!(
+                    "{} new paths do not exist, nothing written.",
+                    missing.len()
+                )));
+            }
+        }
+
+        store_shortcuts(
+            &scs,
+            out.as_ref().unwrap_or(shortcuts_path),
+            *force,
+            *dry_run,
+        )
+    } else {
+        unreachable!();
+    }
+}
+
+fn import_desktop(args: &Cli) -> Result<i32, Error> {
+    if let Commands::ImportDesktop {
+        shortcuts_path,
+        desktop_paths,
+        out,
+        force,
+        dry_run,
+    } = &args.command
+    {
+        let mut scs = load_shortcuts(shortcuts_path)?;
+
+        // Files given by name are always imported, the ones found in a
+        // folder only when the menus show them.
+        let mut files = vec![];
+        for p in desktop_paths {
+            let path = Path::new(p);
+            if path.is_dir() {
+                let entries = std::fs::read_dir(path).map_err(|e| {
+                    Error::InvalidInputFile(format!("Cannot read {}. {:?}", path.display(), e))
+                })?;
+                let mut found: Vec<_> = entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|f| f.extension().is_some_and(|e| e == "desktop"))
+                    .collect();
+                found.sort();
+                files.extend(found.into_iter().map(|f| (f, false)));
+            } else {
+                files.push((path.to_path_buf(), true));
+            }
+        }
+
+        let mut resolver = icons::IconResolver::new(None, icons::DEFAULT_SIZE);
+        let (mut imported, mut skipped) = (0, 0);
+        for (file, explicit) in files {
+            let file = std::path::absolute(&file).unwrap_or(file);
+            let entry = std::fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|text| desktop::DesktopEntry::parse(&text));
+            let result = match entry {
+                Ok(entry) if !explicit && entry.is_hidden() => Err("hidden".to_owned()),
+                Ok(entry) => entry.to_shortcut(&file).map(|mut sc| {
+                    // Icons named after the theme become the file of the
+                    // best size found.
+                    if let Some(path) = entry.get("Icon").and_then(|i| resolver.resolve(i)) {
+                        sc.props.insert(
+                            "icon".to_owned(),
+                            ShortcutProp::String(path.to_string_lossy().into_owned()),
+                        );
+                    }
+                    sc
+                }),
+                Err(e) => Err(e),
+            };
+            let already = scs.iter().any(|sc| {
+                sc.prop_to_string("shortcut_path").as_deref() == file.to_str()
+            });
+            match result {
+                Ok(_) if already => {
+                    println!("Skip {}: already imported", file.display());
+                    skipped += 1;
+                }
+                Ok(sc) => {
+                    scs.push(sc);
+                    imported += 1;
+                }
+                Err(e) => {
+                    println!("Skip {}: {}", file.display(), e);
+                    skipped += 1;
+                }
+            }
+        }
+        println!("{} launchers imported, {} skipped.", imported, skipped);
+        if imported == 0 {
+            return Ok(exitcode::OK);
+        }
+
+        store_shortcuts(
+            &scs,
+            out.as_ref().unwrap_or(shortcuts_path),
+            *force,
+            *dry_run,
+        )
+    } else {
+        unreachable!();
+    }
+}
+
+fn export_desktop(args: &Cli) -> Result<i32, Error> {
+    if let Commands::ExportDesktop {
+        shortcuts_path,
+        out_dir,
+        idx,
+        launch,
+        force,
+        dry_run,
+    } = &args.command
+    {
+        let scs = load_shortcuts(shortcuts_path)?;
+        let out_dir = match out_dir {
+            Some(dir) => std::path::PathBuf::from(dir),
+            None => std::env::var_os("XDG_DATA_HOME")
+                .map(std::path::PathBuf::from)
+                .or_else(|| {
+                    std::env::var_os("HOME")
+                        .map(|h| Path::new(&h).join(".local").join("share"))
+                })
+                .ok_or(Error::InvalidArgument(
+                    "Cannot find the applications folder, use --out-dir.".to_owned(),
+                ))?
+                .join("applications"),
+        };
+        let launch = match launch {
+            DesktopLaunch::Exe => desktop::Launch::Exe,
+            DesktopLaunch::Steam => desktop::Launch::Steam,
+        };
+
+        let mut launchers = vec![];
+        for sc in scs
+            .iter()
+            .filter(|sc| idx.is_empty() || sc.prop_to_u32("index").is_some_and(|i| idx.contains(&i)))
+        {
+            let index = sc.prop_to_u32("index").unwrap_or_default();
+            match desktop::DesktopEntry::from_shortcut(sc, launch) {
+                Ok(entry) => launchers.push((out_dir.join(desktop::file_name(sc)), entry)),
+                Err(e) => println!("Skip [{}]: {}", index, e),
+            }
+        }
+
+        // Unchanged launchers are skipped, changed ones need --force.
+        let mut pending = vec![];
+        for (path, entry) in &launchers {
+            let content = entry.to_string();
+            if std::fs::read_to_string(path).is_ok_and(|c| c == content) {
+                println!("Up to date: {}", path.display());
+            } else if path.exists() && !*force {
+                return Err(Error::InvalidOutputFile(format!(
+                    "Launcher already exists at: \"{}\". Use --force to overwire it.",
+                    path.display()
+                )));
+            } else {
+                pending.push((path, content));
+            }
+        }
+
+        for (path, content) in &pending {
+            if *dry_run {
+                println!("Would write: {}\n{}", path.display(), content);
+                continue;
+            }
+            println!("Write to file: {}", path.display());
+            std::fs::create_dir_all(&out_dir)
+                .and_then(|_| std::fs::write(path, content))
+                .map_err(|e| {
+                    Error::InvalidOutputFile(format!(
+                        "Unable to create file {}. {:?}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+        }
+
+        Ok(if *dry_run && !pending.is_empty() {
+            EXIT_PENDING_CHANGES
+        } else {
+            exitcode::OK
+        })
+    } else {
+        unreachable!();
+    }
+}
+
+fn resolve_icons(args: &Cli) -> Result<i32, Error> {
+    if let Commands::ResolveIcons {
+        shortcuts_path,
+        theme,
+        size,
+        idx,
+        out,
+        force,
+        dry_run,
+    } = &args.command
+    {
+        let mut scs = load_shortcuts(shortcuts_path)?;
+        let mut resolver = icons::IconResolver::new(theme.as_deref(), *size);
+
+        // Entries whose icon is already a file are left alone.
+        let pending: Vec<(u32, String, Vec<String>)> = scs
+            .iter()
+            .filter(|sc| idx.is_empty() || sc.prop_to_u32("index").is_some_and(|i| idx.contains(&i)))
+            .filter(|sc| {
+                let icon = sc.prop_to_string("icon").unwrap_or_default();
+                icon.trim().is_empty() || !Path::new(icon.trim()).is_file()
+            })
+            .map(|sc| {
+                (
+                    sc.prop_to_u32("index").unwrap_or_default(),
+                    sc.prop_to_string("app_name").unwrap_or_default(),
+                    icons::icon_names(sc),
+                )
+            })
+            .collect();
+
+        let mut resolved = 0;
+        for (index, name, names) in &pending {
+            match names.iter().find_map(|n| resolver.resolve(n)) {
+                Some(path) => {
+                    println!("[{}] {:?}: {}", index, name, path.display());
+                    if let Some(sc) = scs.at_mut(index) {
+                        sc.props.insert(
+                            "icon".to_owned(),
+                            ShortcutProp::String(path.to_string_lossy().into_owned()),
+                        );
+                    }
+                    resolved += 1;
+                }
+                None if names.is_empty() => {
+                    println!("[{}] {:?}: no icon name to look for", index, name)
+                }
+                None => println!(
+                    "[{}] {:?}: no icon found for {}",
+                    index,
+                    name,
+                    names.join(", ")
+                ),
+            }
+        }
+        println!("{} of {} icons resolved.", resolved, pending.len());
+        if resolved == 0 {
+            return Ok(exitcode::OK);
+        }
+
+        store_shortcuts(
+            &scs,
+            out.as_ref().unwrap_or(shortcuts_path),
+            *force,
+            *dry_run,
+        )
+    } else {
+        unreachable!();
+    }
+}
+
+fn extract_icons(args: &Cli) -> Result<i32, Error> {
+    if let Commands::ExtractIcons {
+        shortcuts_path,
+        cache_dir,
+        idx,
+        out,
+        force,
+        dry_run,
+    } = &args.command
+    {
+        let mut scs = load_shortcuts(shortcuts_path)?;
+        let cache_dir = cache_dir
+            .as_ref()
+            .map(std::path::PathBuf::from)
+            .or_else(pe::cache_dir)
+            .ok_or_else(|| Error::InvalidArgument("No cache folder, pass --cache-dir.".to_owned()))?;
+
+        // Only Windows programs are looked into; entries whose icon is
+        // already a file are left alone.
+        let pending: Vec<(u32, String, std::path::PathBuf, u32)> = scs
+            .iter()
+            .filter(|sc| idx.is_empty() || sc.prop_to_u32("index").is_some_and(|i| idx.contains(&i)))
+            .filter(|sc| {
+                let icon = sc.prop_to_string("icon").unwrap_or_default();
+                icon.trim().is_empty() || !Path::new(icon.trim()).is_file()
+            })
+            .filter_map(|sc| {
+                let program = desktop::command_line(sc).ok()?.swap_remove(0);
+                if !program.to_lowercase().ends_with(".exe") {
+                    return None;
+                }
+                Some((
+                    sc.prop_to_u32("index").unwrap_or_default(),
+                    sc.prop_to_string("app_name").unwrap_or_default(),
+                    std::path::PathBuf::from(program),
+                    match sc.prop_to_u32("app_id") {
+                        None | Some(0) => sc.compute_app_id(),
+                        Some(id) => id,
+                    },
+                ))
+            })
+            .collect();
+
+        // Icons are written once the shortcuts are stored, so a refused
+        // store leaves no file behind.
+        let mut icons: Vec<(std::path::PathBuf, Vec<u8>)> = vec![];
+        for (index, name, program, app_id) in &pending {
+            let group = std::fs::read(program)
+                .map_err(|e| format!("cannot read {}. {}", program.display(), e))
+                .and_then(|buffer| pe::largest_icon_group(&buffer));
+            let group = match group {
+                Ok(Some(group)) => group,
+                Ok(None) => {
+                    println!("[{}] {:?}: no icon in {}", index, name, program.display());
+                    continue;
+                }
+                Err(e) => {
+                    println!("[{}] {:?}: {}", index, name, e);
+                    continue;
+                }
+            };
+            let (extension, data) = pe::icon_file(&group);
+            let path = cache_dir.join(format!("{}.{}", app_id, extension));
+            println!("[{}] {:?}: {}", index, name, path.display());
+            if let Some(sc) = scs.at_mut(index) {
+                sc.props.insert(
+                    "icon".to_owned(),
+                    ShortcutProp::String(path.to_string_lossy().into_owned()),
+                );
+            }
+            icons.push((path, data));
+        }
+        println!("{} of {} icons extracted.", icons.len(), pending.len());
+        if icons.is_empty() {
+            return Ok(exitcode::OK);
+        }
+
+        let code = store_shortcuts(
+            &scs,
+            out.as_ref().unwrap_or(shortcuts_path),
+            *force,
+            *dry_run,
+        )?;
+        if !*dry_run {
+            for (path, data) in &icons {
+                std::fs::create_dir_all(&cache_dir)
+                    .and_then(|_| std::fs::write(path, data))
+                    .map_err(|e| {
+                        Error::InvalidOutputFile(format!("Cannot write {}. {:?}", path.display(), e))
+                    })?;
+            }
+        }
+        Ok(code)
+    } else {
+        unreachable!();
+    }
+}
+
+fn repair_shortcuts(args: &Cli) -> Result<i32, Error> {
+    if let Commands::Repair {
+        shortcuts_path,
+        out,
+        force,
+        dry_run,
+    } = &args.command
+    {
+        let joined = Path::new(shortcuts_path).join("shortcuts.vdf");
+        let path = if Path::new(shortcuts_path).is_dir() {
+            joined.as_path()
+        } else {
+            Path::new(shortcuts_path)
+        };
+        let buffer = std::fs::read(path).map_err(|e| {
+            Error::InvalidInputFile(format!("Cannot read {}. {:?}", path.display(), e))
+        })?;
+
+        if Shortcuts::parse(&buffer, &mut 0).is_ok() {
+            println!("File is valid, nothing to repair.");
+            return Ok(exitcode::OK);
+        }
+
+        let report = repair::repair(&buffer);
+        for note in &report.notes {
+            println!("{}", note);
+        }
+        let lost = report
+            .notes
+            .iter()
+            .filter(|n| matches!(n, repair::RepairNote::Lost { .. }))
+            .count();
+        println!(
+            "{} entries recovered, {} lost.",
+            report.shortcuts.len(),
+            lost
+        );
+        if report.shortcuts.is_empty() {
+            return Err(Error::InvalidInputFile(
+                "No entry could be recovered, nothing written.".to_owned(),
+            ));
+        }
+
+        store_shortcuts(
+            &report.shortcuts,
+            out.as_ref().unwrap_or(shortcuts_path),
+            *force,
+            *dry_run,
+        )
+    } else {
+        unreachable!();
+    }
+}
+
+fn load_shortcuts(shortcuts_path: &str) -> Result<Shortcuts, Error> {
+    // Ensure shortcuts_vdf path
+    let path_joined = Path::new(&shortcuts_path).join("shortcuts.vdf");
+    let path_raw = Path::new(&shortcuts_path);
+
+    let shortcuts_vdf = match path_raw.file_name() {
+        Some(_) if path_raw.exists() && path_raw.is_file() => path_raw,
+        Some(_) if path_joined.exists() && path_joined.is_file() => Path::new(&path_joined),
+        _ => return Err(Error::InvalidInputFile(String::from(
+            "<SHORTCUTS_PATH> must be an existining file or folder contains .../<shortcuts>.vdf",
+        ))),
+    };
+
+    //println!("Analyze {:?}:", shortcuts_vdf);
+
+    let ret = File::open(shortcuts_vdf.as_os_str().to_str().unwrap());
+    if ret.is_err() {
+        return Err(Error::InvalidInputFile(format!(
+            "{:?} cannot be opened due to: {:?}",
+            shortcuts_vdf, ret
+        )));
+    }
+
+    let f = ret.unwrap();
+
+    let mut reader = BufReader::new(f);
+    let mut buffer = Vec::new();
+
+    // Read file into vector.
+    if reader.read_to_end(&mut buffer).unwrap_or(0) == 0 {
+        return Err(Error::InvalidInputFile(format!(
+            "{:?} cannot be read or is empty.",
+            shortcuts_vdf
+        )));
+    }
+
+    let mut index = 0;
+
+    Shortcuts::parse(&buffer, &mut index).map_err(|err| {
+        Error::InvalidInputFile(format!(
+            "Error while loading file {}. {}",
+            shortcuts_path, err
+        ))
+    })
+}
+
+fn list_shortcuts(args: &Cli) -> Result<(), Error> {
+    if let Commands::List {
+        shortcuts_path,
+        separator,
+        index,
+        app_id,
+        app_name,
+        exe,
+        icon,
+        allow_desktop_config,
         allow_overlay,
         devkit,
         devkit_game_id,
@@ -461,218 +1782,190 @@ fn list_shortcuts(args: &Cli) -> Result<(), Error> {
         start_dir,
         tags,
         all,
+        columns,
         keys,
         json,
-        ..
+        output,
+        tags_delimiter,
+        format,
+        width,
+        sort,
+        limit,
+        encoding,
     } = &args.command
     {
-        let scs = load_shortcuts(shortcuts_path)?;
+        let mut scs = load_shortcuts(shortcuts_path)?;
+        if let Some(label) = encoding {
+            let encoding = encoding_rs::Encoding::for_label(label.as_bytes()).ok_or(
+                Error::InvalidArgument(format!("Unknown encoding \"{}\".", label)),
+            )?;
+            scs.decode_bytes(&|b: &[u8]| {
+                encoding.decode_without_bom_handling(b).0.into_owned()
+            });
+        }
 
-        if *json {
+        let sort_keys = sort
+            .iter()
+            .map(|s| SortKey::parse(s, &scs))
+            .collect::<Result<Vec<SortKey>, String>>()
+            .map_err(Error::InvalidArgument)?;
+        let mut entries: Vec<&shortcuts::Shortcut> = scs.iter().collect();
+        entries.sort_by(|a, b| columns::compare_by(&sort_keys, a, b));
+        if let Some(limit) = limit {
+            entries.truncate(*limit);
+        }
+
+        if let Some(format) = format {
+            let template = Template::parse(format, &scs).map_err(Error::InvalidArgument)?;
+            for sc in &entries {
+                println!("{}", template.render(sc));
+            }
+            return Ok(());
+        }
+
+        let any_padded = all == &ListColumnsModes::Padded
+            || [
+                index, app_id, app_name, exe, icon, allow_desktop_config, allow_overlay, devkit,
+                devkit_game_id, devkit_override_app_id, flatpak_app_id, is_hidden, last_play_time,
+                last_play_time_fmt, last_play_time_utc, last_play_time_iso, launch_options,
+                open_vr, shortcut_path, start_dir, tags,
+            ]
+            .contains(&&ListColumnsModes::Padded);
+        let output = if *json {
+            ListOutput::Json
+        } else if let Some(o) = output {
+            o.clone()
+        } else if any_padded {
+            ListOutput::Table
+        } else {
+            ListOutput::Plain
+        };
+
+        if output == ListOutput::Json {
             println!(
-                "[{}]",
-                scs.iter()
-                    .map(|sc| format!(
-                        "{{{}}}",
-                        sc.props
-                            .keys()
-                            .sorted()
-                            .map(|k| format!(
-                                "{:?}:{}",
-                                k,
-                                match sc.props.get(k) {
-                                    Some(shortcuts::ShortcutProp::UInt32(u)) => u.to_string(),
-                                    Some(shortcuts::ShortcutProp::String(s)) => format!("{:?}", s),
-                                    Some(shortcuts::ShortcutProp::Strings(s)) => format!("{:?}", s),
-                                    _ => "".to_owned(),
-                                }
-                            ))
-                            .collect::<Vec<String>>()
-                            .join(",")
-                    ))
-                    .collect::<Vec<String>>()
-                    .join(",")
+                "{}",
+                serde_json::Value::Array(
+                    entries
+                        .iter()
+                        .map(|sc| serde_json::Value::Object(sc.to_json()))
+                        .collect()
+                )
+            )
+        } else if output == ListOutput::Ndjson {
+            println!("{}", formats::to_ndjson(&entries))
+        } else if output == ListOutput::Yaml {
+            print!("{}", formats::to_yaml(&entries).map_err(Error::InvalidOutputFile)?)
+        } else if output == ListOutput::Toml {
+            print!("{}", formats::to_toml(&entries).map_err(Error::InvalidOutputFile)?)
+        } else if output == ListOutput::Csv || output == ListOutput::Tsv {
+            let selected = if !columns.is_empty() {
+                columns::resolve(columns, &scs).map_err(Error::InvalidArgument)?
+            } else {
+                SHORTCUT_PROP_INFO
+                    .iter()
+                    .map(Column::Prop)
+                    .chain(columns::extra_keys(&scs).into_iter().map(Column::Extra))
+                    .collect()
+            };
+            print!(
+                "{}",
+                formats::to_delimited(
+                    &entries,
+                    &selected,
+                    if output == ListOutput::Tsv {
+                        RecordsFormat::Tsv
+                    } else {
+                        RecordsFormat::Csv
+                    },
+                    tags_delimiter,
+                )
+                .map_err(Error::InvalidOutputFile)?
             )
         } else {
+            let selected = if !columns.is_empty() {
+                columns::resolve(columns, &scs).map_err(Error::InvalidArgument)?
+            } else {
+                // Per-field flags, in SHORTCUT_PROP_INFO order.
+                let flags = [
+                    (index, "index"),
+                    (app_id, "app_id"),
+                    (app_name, "app_name"),
+                    (exe, "exe"),
+                    (start_dir, "start_dir"),
+                    (icon, "icon"),
+                    (shortcut_path, "shortcut_path"),
+                    (launch_options, "launch_options"),
+                    (is_hidden, "is_hidden"),
+                    (allow_desktop_config, "allow_desktop_config"),
+                    (allow_overlay, "allow_overlay"),
+                    (open_vr, "open_vr"),
+                    (devkit, "devkit"),
+                    (devkit_game_id, "devkit_game_id"),
+                    (devkit_override_app_id, "devkit_override_app_id"),
+                    (last_play_time, "last_play_time"),
+                    (last_play_time_fmt, "last_play_time_fmt"),
+                    (last_play_time_iso, "last_play_time_iso"),
+                    (last_play_time_utc, "last_play_time_utc"),
+                    (flatpak_app_id, "flatpak_app_id"),
+                    (tags, "tags"),
+                ];
+                let mut selected: Vec<Column> = flags
+                    .iter()
+                    .filter(|(mode, _)| {
+                        (if all != &ListColumnsModes::None { all } else { mode })
+                            != &ListColumnsModes::None
+                    })
+                    .filter_map(|(_, name)| Column::parse(name))
+                    .collect();
+                if all != &ListColumnsModes::None {
+                    selected.extend(columns::extra_keys(&scs).into_iter().map(Column::Extra));
+                }
+                selected
+            };
+
+            if output == ListOutput::Table {
+                let max_width = match width {
+                    Some(0) => None,
+                    Some(w) => Some(*w),
+                    None => terminal_size::terminal_size().map(|(w, _)| w.0 as usize),
+                };
+                println!(
+                    "{}",
+                    table::render(
+                        &selected
+                            .iter()
+                            .map(|c| table::TableColumn {
+                                header: c.name().to_owned(),
+                                align_right: c.is_numeric(),
+                                truncatable: !c.is_numeric(),
+                            })
+                            .collect::<Vec<table::TableColumn>>(),
+                        &entries
+                            .iter()
+                            .map(|sc| selected.iter().map(|c| c.text(sc)).collect())
+                            .collect::<Vec<Vec<String>>>(),
+                        max_width,
+                    )
+                );
+                return Ok(());
+            }
+
             println!(
                 "{}",
-                scs.iter()
+                entries
+                    .iter()
                     .map(|sc| {
-                        format_column_output!(sc, keys, all, index, u32, "index", "{}")
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                allow_desktop_config,
-                                u32,
-                                "allow_desktop_config",
-                                "{}"
-                            ))
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                allow_overlay,
-                                u32,
-                                "allow_overlay",
-                                "{}"
-                            ))
-                            .chain(format_column_output!(
-                                sc, keys, all, app_id, u32, "app_id", "{}"
-                            ))
-                            .chain(format_column_output!(
-                                sc, keys, all, app_name, String, "app_name", "\"{}\""
-                            ))
-                            .chain(format_column_output!(
-                                sc, keys, all, devkit, u32, "devkit", "{}"
-                            ))
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                devkit_game_id,
-                                String,
-                                "devkit_game_id",
-                                "{}"
-                            ))
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                devkit_override_app_id,
-                                u32,
-                                "devkit_override_app_id",
-                                "{}"
-                            ))
-                            .chain(format_column_output!(
-                                sc, keys, all, exe, String, "exe", "\"{}\""
-                            ))
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                flatpak_app_id,
-                                String,
-                                "flatpak_app_id",
-                                "\"{}\""
-                            ))
-                            .chain(format_column_output!(
-                                sc, keys, all, icon, String, "icon", "\"{}\""
-                            ))
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                is_hidden,
-                                u32,
-                                "is_hidden",
-                                "{}"
-                            ))
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                last_play_time,
-                                u32,
-                                "last_play_time",
-                                "{}"
-                            ))
-                            .chain(format_column_output_ex!(
-                                keys,
-                                all,
-                                last_play_time_fmt,
-                                last_play_time,
-                                NaiveDateTime::from_timestamp(
-                                    TryInto::<u32>::try_into(
-                                        sc.props.get("last_play_time").unwrap_or_default()
-                                    )
-                                    .unwrap() as i64,
-                                    0
-                                ),
-                                "\"{}\""
-                            ))
-                            .chain(format_column_output_ex!(
-                                keys,
-                                all,
-                                last_play_time_iso,
-                                last_play_time,
-                                NaiveDateTime::from_timestamp(
-                                    TryInto::<u32>::try_into(
-                                        sc.props.get("last_play_time").unwrap_or_default()
-                                    )
-                                    .unwrap() as i64,
-                                    0
-                                ),
-                                "\"{}\""
-                            ))
-                            .chain(format_column_output_ex!(
-                                keys,
-                                all,
-                                last_play_time_utc,
-                                last_play_time,
-                                DateTime::<Utc>::from_utc(
-                                    NaiveDateTime::from_timestamp(
-                                        TryInto::<u32>::try_into(
-                                            sc.props.get("last_play_time").unwrap_or_default()
-                                        )
-                                        .unwrap() as i64,
-                                        0
-                                    ),
-                                    Utc
-                                ),
-                                "\"{}\""
-                            ))
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                launch_options,
-                                String,
-                                "launch_options",
-                                "\"{}\""
-                            ))
-                            .chain(format_column_output!(
-                                sc, keys, all, open_vr, u32, "open_vr", "{}"
-                            ))
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                shortcut_path,
-                                String,
-                                "shortcut_path",
-                                "\"{}\""
-                            ))
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                start_dir,
-                                String,
-                                "start_dir",
-                                "\"{}\""
-                            ))
-                            .chain(format_column_output!(
-                                sc,
-                                keys,
-                                all,
-                                tags,
-                                Vec<String>,
-                                "tags",
-                                "{:?}"
-                            ))
-                            .filter_map(|e| e)
-                            .sorted_by(|(a, _), (b, _)| Ord::cmp(&a.order, &b.order))
-                            .map(|(_, v)| v)
-                            .collect::<Vec<String>>()
-                            .join(
-                                /*if *keys {
-                                    format!(stringify!(",{}"),separator)
+                        selected
+                            .iter()
+                            .map(|c| {
+                                if *keys {
+                                    format!("{} = {}", c.name(), c.plain(sc))
                                 } else {
-                                    format!("{}",separator)
-                                }.as_str()*/
-                                separator,
-                            )
+                                    c.plain(sc)
+                                }
+                            })
+                            .collect::<Vec<String>>()
+                            .join(separator)
                     })
                     .collect::<Vec<String>>()
                     .join("\n")
@@ -691,4 +1984,5 @@ enum Error {
     InvalidOutputFile(String),
     IvalidUInt32Passed(String),
     IvalidStringsPassed(String),
+    InvalidArgument(String),
 }
@@ -0,0 +1,318 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use std::io::{BufRead, Write};
+
+use crate::diff;
+use crate::shortcuts::{Shortcut, ShortcutProp, Shortcuts};
+
+/// Field used to recognize the same shortcut in different inputs.
+#[derive(Debug, Clone, Copy)]
+pub enum MergeKey {
+    /// App id. A missing or 0 one is computed like Steam does.
+    AppId,
+    /// Exe path, ignoring the quotes Steam adds around it.
+    Exe,
+}
+
+impl MergeKey {
+    fn of(&self, sc: &Shortcut) -> Option<String> {
+        match self {
+            MergeKey::AppId => Some(
+                match sc.prop_to_u32("app_id") {
+                    None | Some(0) => sc.compute_app_id(),
+                    Some(id) => id,
+                }
+                .to_string(),
+            ),
+            MergeKey::Exe => sc
+                .prop_to_string("exe")
+                .map(|e| e.trim().trim_matches('"').to_owned())
+                .filter(|e| !e.is_empty()),
+        }
+    }
+}
+
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+/// Two values of the same field. "Ours" is the entry met first, in the
+/// order the inputs were given.
+pub struct Conflict<'a> {
+    pub name: String,
+    pub field: &'a str,
+    pub ours: &'a ShortcutProp,
+    pub theirs: &'a ShortcutProp,
+}
+
+pub enum Policy<'a> {
+    Ours,
+    Theirs,
+    /// Values of the entry with the most recent `last_play_time`, ours on a tie.
+    Newest,
+    /// Ask for every conflict.
+    Interactive(&'a mut dyn FnMut(&Conflict) -> Result<Side, String>),
+}
+
+/// Print `conflict` to `prompt` and read which side to keep from `input`,
+/// asking again until the answer is valid.
+pub fn ask(
+    conflict: &Conflict,
+    input: &mut impl BufRead,
+    prompt: &mut impl Write,
+) -> Result<Side, String> {
+    loop {
+        write!(
+            prompt,
+            "Conflict on {:?} field {}:\n  [o]urs:   {}\n  [t]heirs: {}\nKeep [o/t]? ",
+            conflict.name,
+            conflict.field,
+            diff::show(conflict.ours),
+            diff::show(conflict.theirs)
+        )
+        .and_then(|_| prompt.flush())
+        .map_err(|e| e.to_string())?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer).map_err(|e| e.to_string())? == 0 {
+            return Err("No answer given for a merge conflict.".to_owned());
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "o" | "ours" => return Ok(Side::Ours),
+            "t" | "theirs" => return Ok(Side::Theirs),
+            _ => (),
+        }
+    }
+}
+
+/// Union of every input. Entries sharing the same `key` are merged into the
+/// first one: tags are joined, missing fields are filled and differing
+/// fields are resolved by `policy`. Entries without a key are always kept.
+/// Returns the merged shortcuts, renumbered, and a log of what was done.
+pub fn merge(
+    inputs: &[Shortcuts],
+    key: MergeKey,
+    policy: &mut Policy,
+) -> Result<(Shortcuts, Vec<String>), String> {
+    let mut merged: Vec<Shortcut> = vec![];
+    let mut log = vec![];
+
+    for (n, scs) in inputs.iter().enumerate() {
+        for theirs in scs.iter() {
+            let k = key.of(theirs);
+            let found = k
+                .as_ref()
+                .and_then(|k| merged.iter().position(|m| key.of(m).as_ref() == Some(k)));
+            match found {
+                Some(pos) => {
+                    let fields = merge_into(&mut merged[pos], theirs, policy)?;
+                    log.push(format!(
+                        "~ {:?} of input {} merged into [{}]{}",
+                        name(theirs),
+                        n + 1,
+                        pos,
+                        if fields.is_empty() {
+                            String::new()
+                        } else {
+                            format!(": {}", fields.join(", "))
+                        }
+                    ));
+                }
+                None => merged.push(theirs.clone()),
+            }
+        }
+    }
+
+    let mut out = Shortcuts::empty();
+    for sc in merged {
+        out.push(sc);
+    }
+    Ok((out, log))
+}
+
+fn name(sc: &Shortcut) -> String {
+    sc.prop_to_string("app_name").unwrap_or_default()
+}
+
+/// Merge `theirs` into `ours`, returning a description of the changed fields.
+fn merge_into(
+    ours: &mut Shortcut,
+    theirs: &Shortcut,
+    policy: &mut Policy,
+) -> Result<Vec<String>, String> {
+    let newest_is_theirs = theirs.prop_to_u32("last_play_time").unwrap_or(0)
+        > ours.prop_to_u32("last_play_time").unwrap_or(0);
+    let mut changed = vec![];
+
+    for change in diff::field_changes(ours, theirs) {
+        let value = match (&change.old, &change.new) {
+            (_, ShortcutProp::None) => continue,
+            (ShortcutProp::None, new) => new.clone(),
+            (ShortcutProp::Strings(a), ShortcutProp::Strings(b)) => ShortcutProp::Strings(
+                a.iter()
+                    .chain(b.iter().filter(|t| !a.contains(t)))
+                    .cloned()
+                    .collect(),
+            ),
+            (old, new) => {
+                let side = match policy {
+                    Policy::Ours => Side::Ours,
+                    Policy::Theirs => Side::Theirs,
+                    Policy::Newest if newest_is_theirs => Side::Theirs,
+                    Policy::Newest => Side::Ours,
+                    Policy::Interactive(ask) => ask(&Conflict {
+                        name: name(ours),
+                        field: &change.field,
+                        ours: old,
+                        theirs: new,
+                    })?,
+                };
+                match side {
+                    Side::Ours => continue,
+                    Side::Theirs => new.clone(),
+                }
+            }
+        };
+        if ours.props.get(&change.field) != Some(&value) {
+            changed.push(format!(
+                "{} {} → {}",
+                change.field,
+                diff::show(&change.old),
+                diff::show(&value)
+            ));
+            ours.props.insert(change.field, value);
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, exe: &str) -> Shortcut {
+        Shortcut::empty()
+            .with_string("app_name", name)
+            .with_string("exe", exe)
+    }
+
+    #[test]
+    fn entries_without_app_id_are_told_apart() {
+        let inputs = [
+            Shortcuts::from_entries([entry("A", "/bin/a")]),
+            Shortcuts::from_entries([entry("B", "/bin/b"), entry("A", "/bin/a")]),
+        ];
+        let (merged, _) = merge(&inputs, MergeKey::AppId, &mut Policy::Ours).unwrap();
+        let names: Vec<String> = merged
+            .iter()
+            .map(|sc| sc.prop_to_string("app_name").unwrap())
+            .collect();
+        assert_eq!(names, ["A", "B"]);
+    }
+
+    #[test]
+    fn zero_app_id_is_computed() {
+        let with_zero = entry("A", "/bin/a").with_u32("app_id", 0);
+        let id = entry("A", "/bin/a").compute_app_id();
+        let with_id = entry("A", "/bin/a").with_u32("app_id", id);
+        let inputs = [
+            Shortcuts::from_entries([with_zero, entry("B", "/bin/b")]),
+            Shortcuts::from_entries([with_id]),
+        ];
+        let (merged, _) = merge(&inputs, MergeKey::AppId, &mut Policy::Ours).unwrap();
+        assert_eq!(merged.len(), 2);
+    }
+
+    /// The same game in two files: ours played at 10, theirs at 20.
+    fn inputs() -> [Shortcuts; 2] {
+        [
+            Shortcuts::from_entries([entry("A", "/bin/a")
+                .with_string("launch_options", "-ours")
+                .with_u32("last_play_time", 10)
+                .with_tags(&["x", "y"])]),
+            Shortcuts::from_entries([entry("A", "/bin/a")
+                .with_string("launch_options", "-theirs")
+                .with_string("icon", "/a.png")
+                .with_u32("last_play_time", 20)
+                .with_tags(&["y", "z"])]),
+        ]
+    }
+
+    fn merged(policy: &mut Policy) -> Result<Shortcut, String> {
+        let (merged, _) = merge(&inputs(), MergeKey::Exe, policy)?;
+        assert_eq!(merged.len(), 1);
+        Ok(merged.at(&0).unwrap().clone())
+    }
+
+    fn text(sc: &Shortcut, key: &str) -> String {
+        sc.prop_to_string(key).unwrap()
+    }
+
+    #[test]
+    fn tags_are_joined_and_missing_fields_filled() {
+        let sc = merged(&mut Policy::Ours).unwrap();
+        assert_eq!(
+            sc.props.get("tags"),
+            Some(&ShortcutProp::Strings(vec![
+                "x".to_owned(),
+                "y".to_owned(),
+                "z".to_owned()
+            ]))
+        );
+        assert_eq!(text(&sc, "icon"), "/a.png");
+    }
+
+    #[test]
+    fn conflicts_follow_the_policy() {
+        let ours = merged(&mut Policy::Ours).unwrap();
+        assert_eq!(text(&ours, "launch_options"), "-ours");
+        assert_eq!(ours.prop_to_u32("last_play_time"), Some(10));
+        let theirs = merged(&mut Policy::Theirs).unwrap();
+        assert_eq!(text(&theirs, "launch_options"), "-theirs");
+        assert_eq!(theirs.prop_to_u32("last_play_time"), Some(20));
+        let newest = merged(&mut Policy::Newest).unwrap();
+        assert_eq!(text(&newest, "launch_options"), "-theirs");
+
+        let [ours, theirs] = inputs();
+        let (merged, _) = merge(&[theirs, ours], MergeKey::Exe, &mut Policy::Newest).unwrap();
+        assert_eq!(text(merged.at(&0).unwrap(), "launch_options"), "-theirs");
+    }
+
+    #[test]
+    fn interactive_policy_asks_for_each_conflict() {
+        let mut input = std::io::Cursor::new("what\nt\no\n");
+        let mut prompt = vec![];
+        let mut asked = vec![];
+        let mut ask_each = |c: &Conflict| {
+            asked.push(c.field.to_owned());
+            ask(c, &mut input, &mut prompt)
+        };
+        let sc = merged(&mut Policy::Interactive(&mut ask_each)).unwrap();
+        assert_eq!(asked, ["last_play_time", "launch_options"]);
+        assert_eq!(sc.prop_to_u32("last_play_time"), Some(20));
+        assert_eq!(text(&sc, "launch_options"), "-ours");
+        let prompt = String::from_utf8(prompt).unwrap();
+        assert!(prompt.starts_with(
+            "Conflict on \"A\" field last_play_time:\n  [o]urs:   10\n  [t]heirs: 20\nKeep [o/t]? "
+        ));
+        // The invalid answer is asked again.
+        assert_eq!(prompt.matches("Keep [o/t]?").count(), 3);
+    }
+
+    #[test]
+    fn interactive_policy_fails_without_an_answer() {
+        let mut ask_eof = |c: &Conflict| ask(c, &mut std::io::empty(), &mut std::io::sink());
+        assert_eq!(
+            merged(&mut Policy::Interactive(&mut ask_eof)).unwrap_err(),
+            "No answer given for a merge conflict."
+        );
+    }
+}
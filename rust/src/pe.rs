@@ -0,0 +1,463 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Icons embedded in the resources of Windows executables (PE files), read
+//! without Wine. Every offset comes from the file, so every read is checked.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const RT_ICON: u32 = 3;
+const RT_GROUP_ICON: u32 = 14;
+/// Index of the resource table in the data directories.
+const RESOURCE_DIRECTORY: usize = 2;
+
+/// One image of an icon: a PNG file or a BMP without its file header, as
+/// stored in `.ico` files.
+pub struct IconImage {
+    pub width: u32,
+    pub height: u32,
+    pub color_count: u8,
+    pub planes: u16,
+    pub bit_count: u16,
+    pub data: Vec<u8>,
+}
+
+impl IconImage {
+    pub fn is_png(&self) -> bool {
+        self.data.starts_with(b"\x89PNG\r\n\x1a\n")
+    }
+}
+
+/// The images of one icon, the ones a `.ico` file holds.
+pub struct IconGroup {
+    pub images: Vec<IconImage>,
+}
+
+impl IconGroup {
+    /// The image with the most pixels, then the most colors.
+    pub fn largest(&self) -> Option<&IconImage> {
+        self.images
+            .iter()
+            .rev()
+            .max_by_key(|i| (i.width * i.height, i.bit_count))
+    }
+
+    /// The group as a `.ico` file.
+    pub fn to_ico(&self) -> Vec<u8> {
+        let mut out = vec![];
+        out.extend(0u16.to_le_bytes());
+        out.extend(1u16.to_le_bytes());
+        out.extend((self.images.len() as u16).to_le_bytes());
+        let mut offset = 6 + 16 * self.images.len();
+        for image in &self.images {
+            // 256 pixels are written as 0.
+            out.push(image.width.min(256) as u8);
+            out.push(image.height.min(256) as u8);
+            out.push(image.color_count);
+            out.push(0);
+            out.extend(image.planes.to_le_bytes());
+            out.extend(image.bit_count.to_le_bytes());
+            out.extend((image.data.len() as u32).to_le_bytes());
+            out.extend((offset as u32).to_le_bytes());
+            offset += image.data.len();
+        }
+        for image in &self.images {
+            out.extend(&image.data);
+        }
+        out
+    }
+}
+
+fn slice(buffer: &[u8], offset: usize, len: usize) -> Result<&[u8], String> {
+    offset
+        .checked_add(len)
+        .and_then(|end| buffer.get(offset..end))
+        .ok_or(format!(
+            "truncated file, {} bytes at 0x{:x} expected",
+            len, offset
+        ))
+}
+
+fn u16_at(buffer: &[u8], offset: usize) -> Result<u16, String> {
+    Ok(u16::from_le_bytes(
+        slice(buffer, offset, 2)?.try_into().unwrap(),
+    ))
+}
+
+fn u32_at(buffer: &[u8], offset: usize) -> Result<u32, String> {
+    Ok(u32::from_le_bytes(
+        slice(buffer, offset, 4)?.try_into().unwrap(),
+    ))
+}
+
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+}
+
+/// Headers of a PE file: enough to find the resources.
+struct Pe {
+    sections: Vec<Section>,
+    /// Address and size of the resource table.
+    resources: Option<(u32, u32)>,
+}
+
+impl Pe {
+    fn parse(buffer: &[u8]) -> Result<Self, String> {
+        if !buffer.starts_with(b"MZ") {
+            return Err("not a Windows executable".to_owned());
+        }
+        let pe = u32_at(buffer, 0x3C)? as usize;
+        if slice(buffer, pe, 4)? != b"PE\0\0" {
+            return Err("not a PE executable".to_owned());
+        }
+        let coff = pe + 4;
+        let section_count = u16_at(buffer, coff + 2)? as usize;
+        let optional = coff + 20;
+        let optional_size = u16_at(buffer, coff + 16)? as usize;
+        let directories = match u16_at(buffer, optional)? {
+            0x10B => optional + 96,
+            0x20B => optional + 112,
+            magic => return Err(format!("unknown optional header 0x{:x}", magic)),
+        };
+        let directory_count = u32_at(buffer, directories - 4)? as usize;
+        let resources = if directory_count > RESOURCE_DIRECTORY {
+            let entry = directories + 8 * RESOURCE_DIRECTORY;
+            Some((u32_at(buffer, entry)?, u32_at(buffer, entry + 4)?))
+                .filter(|(address, _)| *address != 0)
+        } else {
+            None
+        };
+
+        let table = optional + optional_size;
+        let sections = (0..section_count)
+            .map(|n| {
+                let header = table + 40 * n;
+                Ok(Section {
+                    virtual_size: u32_at(buffer, header + 8)?,
+                    virtual_address: u32_at(buffer, header + 12)?,
+                    raw_offset: u32_at(buffer, header + 20)?,
+                })
+            })
+            .collect::<Result<Vec<Section>, String>>()?;
+        Ok(Self {
+            sections,
+            resources,
+        })
+    }
+
+    /// Offset in the file of a relative virtual address.
+    fn offset(&self, rva: u32) -> Result<usize, String> {
+        self.sections
+            .iter()
+            .find(|s| rva >= s.virtual_address && rva - s.virtual_address < s.virtual_size.max(1))
+            .map(|s| s.raw_offset as usize + (rva - s.virtual_address) as usize)
+            .ok_or(format!("address 0x{:x} is in no section", rva))
+    }
+}
+
+/// Entries of a resource directory: id (`None` for named ones) and offset
+/// of the subdirectory or data entry, from the start of the resources.
+fn directory_entries(
+    buffer: &[u8],
+    root: usize,
+    offset: usize,
+) -> Result<Vec<(Option<u32>, usize, bool)>, String> {
+    let directory = root + offset;
+    let count = u16_at(buffer, directory + 12)? as usize + u16_at(buffer, directory + 14)? as usize;
+    (0..count)
+        .map(|n| {
+            let entry = directory + 16 + 8 * n;
+            let name = u32_at(buffer, entry)?;
+            let target = u32_at(buffer, entry + 4)?;
+            Ok((
+                (name & 0x8000_0000 == 0).then_some(name),
+                (target & 0x7FFF_FFFF) as usize,
+                target & 0x8000_0000 != 0,
+            ))
+        })
+        .collect()
+}
+
+/// Id of a resource, `None` for named ones, and its data.
+type Resource = (Option<u32>, Vec<u8>);
+
+/// Every resource of `kind`, in file order. Only the first language of each
+/// resource is read.
+fn resources(pe: &Pe, buffer: &[u8], kind: u32) -> Result<Vec<Resource>, String> {
+    let Some((address, _)) = pe.resources else {
+        return Ok(vec![]);
+    };
+    let root = pe.offset(address)?;
+    let mut found = vec![];
+    for (id, offset, is_dir) in directory_entries(buffer, root, 0)? {
+        if id != Some(kind) || !is_dir {
+            continue;
+        }
+        for (name, offset, is_dir) in directory_entries(buffer, root, offset)? {
+            if !is_dir {
+                continue;
+            }
+            let Some((_, data_entry, false)) =
+                directory_entries(buffer, root, offset)?.first().copied()
+            else {
+                continue;
+            };
+            let data_entry = root + data_entry;
+            let data = pe.offset(u32_at(buffer, data_entry)?)?;
+            let size = u32_at(buffer, data_entry + 4)? as usize;
+            found.push((name, slice(buffer, data, size)?.to_vec()));
+        }
+    }
+    Ok(found)
+}
+
+/// Every icon of a PE file, in the order of its resources: the first one is
+/// the one Windows shows for the file.
+pub fn icon_groups(buffer: &[u8]) -> Result<Vec<IconGroup>, String> {
+    let pe = Pe::parse(buffer)?;
+    let icons: HashMap<u32, Vec<u8>> = resources(&pe, buffer, RT_ICON)?
+        .into_iter()
+        .filter_map(|(id, data)| id.map(|id| (id, data)))
+        .collect();
+
+    let mut groups = vec![];
+    for (_, group) in resources(&pe, buffer, RT_GROUP_ICON)? {
+        let count = u16_at(&group, 4)? as usize;
+        let mut images = vec![];
+        for n in 0..count {
+            let entry = 6 + 14 * n;
+            let id = u16_at(&group, entry + 12)? as u32;
+            // Entries pointing to a missing image are skipped, as Windows does.
+            let Some(data) = icons.get(&id) else {
+                continue;
+            };
+            let size = |b: u8| if b == 0 { 256 } else { b as u32 };
+            images.push(IconImage {
+                width: size(slice(&group, entry, 1)?[0]),
+                height: size(slice(&group, entry + 1, 1)?[0]),
+                color_count: slice(&group, entry + 2, 1)?[0],
+                planes: u16_at(&group, entry + 4)?,
+                bit_count: u16_at(&group, entry + 6)?,
+                data: data.clone(),
+            });
+        }
+        if !images.is_empty() {
+            groups.push(IconGroup { images });
+        }
+    }
+    Ok(groups)
+}
+
+/// The icon group with the largest image, the first one on a tie.
+pub fn largest_icon_group(buffer: &[u8]) -> Result<Option<IconGroup>, String> {
+    Ok(icon_groups(buffer)?.into_iter().rev().max_by_key(|g| {
+        g.largest()
+            .map(|i| (i.width * i.height, i.bit_count))
+            .unwrap_or_default()
+    }))
+}
+
+/// Folder where the icons taken out of executables are saved:
+/// `$XDG_CACHE_HOME/steam-shortcuts-editor/icons` (`~/.cache/...`).
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(d) if !d.is_empty() => PathBuf::from(d),
+        _ => Path::new(&std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(cache.join("steam-shortcuts-editor").join("icons"))
+}
+
+/// Extension and content of the file to save `group` to: its largest image
+/// when that is a PNG, the whole group as `.ico` otherwise.
+pub fn icon_file(group: &IconGroup) -> (&'static str, Vec<u8>) {
+    match group.largest() {
+        Some(image) if image.is_png() => ("png", image.data.clone()),
+        _ => ("ico", group.to_ico()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Resource directory with `entries` of (id, offset, is a subdirectory).
+    fn directory(entries: &[(u32, u32, bool)]) -> Vec<u8> {
+        let mut out = vec![0; 12];
+        out.extend(0u16.to_le_bytes());
+        out.extend((entries.len() as u16).to_le_bytes());
+        for (id, offset, is_dir) in entries {
+            out.extend(id.to_le_bytes());
+            out.extend((offset | if *is_dir { 0x8000_0000 } else { 0 }).to_le_bytes());
+        }
+        out
+    }
+
+    /// A 14 bytes `GRPICONDIRENTRY` of an icon group.
+    fn group_entry(size: u8, bit_count: u16, data_len: usize, id: u16) -> Vec<u8> {
+        let mut out = vec![size, size, 0, 0];
+        out.extend(1u16.to_le_bytes());
+        out.extend(bit_count.to_le_bytes());
+        out.extend((data_len as u32).to_le_bytes());
+        out.extend(id.to_le_bytes());
+        out
+    }
+
+    /// A PE32 file with one section holding the resources: `icons` as
+    /// RT_ICON 1, 2, ... and one RT_GROUP_ICON listing them all.
+    fn pe_with_icons(icons: &[(u8, u16, &[u8])]) -> Vec<u8> {
+        const SECTION_RVA: u32 = 0x1000;
+        const SECTION_OFFSET: usize = 0x200;
+
+        let mut group = vec![0, 0, 1, 0];
+        group.extend((icons.len() as u16).to_le_bytes());
+        for (n, (size, bit_count, data)) in icons.iter().enumerate() {
+            group.extend(group_entry(*size, *bit_count, data.len(), n as u16 + 1));
+        }
+        let mut datas: Vec<&[u8]> = icons.iter().map(|(_, _, d)| *d).collect();
+        datas.push(&group);
+
+        // root, RT_ICON, its names, RT_GROUP_ICON, its name, then one
+        // language directory per resource, the data entries and the data.
+        let count = datas.len() as u32;
+        let dir_size = |n: u32| 16 + 8 * n;
+        let root = 0;
+        let icon_names = root + dir_size(2);
+        let group_names = icon_names + dir_size(count - 1);
+        let languages = group_names + dir_size(1);
+        let data_entries = languages + count * dir_size(1);
+        let mut data_offset = data_entries + 16 * count;
+
+        let mut resources = directory(&[
+            (RT_ICON, icon_names, true),
+            (RT_GROUP_ICON, group_names, true),
+        ]);
+        let language = |n: u32| languages + n * dir_size(1);
+        resources.extend(directory(
+            &(0..count - 1)
+                .map(|n| (n + 1, language(n), true))
+                .collect::<Vec<_>>(),
+        ));
+        resources.extend(directory(&[(1, language(count - 1), true)]));
+        for n in 0..count {
+            resources.extend(directory(&[(0x409, data_entries + 16 * n, false)]));
+        }
+        for data in &datas {
+            resources.extend((SECTION_RVA + data_offset).to_le_bytes());
+            resources.extend((data.len() as u32).to_le_bytes());
+            resources.extend([0; 8]);
+            data_offset += data.len() as u32;
+        }
+        for data in &datas {
+            resources.extend(*data);
+        }
+
+        let mut file = vec![0; SECTION_OFFSET];
+        file[..2].copy_from_slice(b"MZ");
+        file[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        file[0x40..0x44].copy_from_slice(b"PE\0\0");
+        let coff = 0x44;
+        file[coff + 2..coff + 4].copy_from_slice(&1u16.to_le_bytes());
+        file[coff + 16..coff + 18].copy_from_slice(&224u16.to_le_bytes());
+        let optional = coff + 20;
+        file[optional..optional + 2].copy_from_slice(&0x10Bu16.to_le_bytes());
+        file[optional + 92..optional + 96].copy_from_slice(&16u32.to_le_bytes());
+        let entry = optional + 96 + 8 * RESOURCE_DIRECTORY;
+        file[entry..entry + 4].copy_from_slice(&SECTION_RVA.to_le_bytes());
+        file[entry + 4..entry + 8].copy_from_slice(&(resources.len() as u32).to_le_bytes());
+        let section = optional + 224;
+        file[section + 8..section + 12].copy_from_slice(&(resources.len() as u32).to_le_bytes());
+        file[section + 12..section + 16].copy_from_slice(&SECTION_RVA.to_le_bytes());
+        file[section + 20..section + 24].copy_from_slice(&(SECTION_OFFSET as u32).to_le_bytes());
+        file.extend(resources);
+        file
+    }
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\nimage";
+
+    #[test]
+    fn icons_are_read_from_the_resources() {
+        let file = pe_with_icons(&[(16, 8, b"small"), (0, 32, PNG)]);
+        let groups = icon_groups(&file).unwrap();
+        assert_eq!(groups.len(), 1);
+        let images = &groups[0].images;
+        assert_eq!(images.len(), 2);
+        assert_eq!((images[0].width, images[0].bit_count), (16, 8));
+        assert_eq!(images[0].data, b"small");
+        // 0 is 256 pixels.
+        assert_eq!((images[1].width, images[1].height), (256, 256));
+
+        let largest = largest_icon_group(&file).unwrap().unwrap();
+        assert_eq!(icon_file(&largest), ("png", PNG.to_vec()));
+    }
+
+    #[test]
+    fn groups_without_png_are_saved_as_ico() {
+        let file = pe_with_icons(&[(32, 32, b"bitmap")]);
+        let group = largest_icon_group(&file).unwrap().unwrap();
+        let (extension, ico) = icon_file(&group);
+        assert_eq!(extension, "ico");
+        assert_eq!(&ico[..6], &[0, 0, 1, 0, 1, 0]);
+        // Directory entry, then the image right after it.
+        assert_eq!(ico[6], 32);
+        assert_eq!(&ico[18..22], &22u32.to_le_bytes());
+        assert_eq!(&ico[22..], b"bitmap");
+    }
+
+    #[test]
+    fn largest_image_has_most_pixels_then_colors() {
+        let image = |size: u32, bit_count: u16, data: &[u8]| IconImage {
+            width: size,
+            height: size,
+            color_count: 0,
+            planes: 1,
+            bit_count,
+            data: data.to_vec(),
+        };
+        let group = IconGroup {
+            images: vec![
+                image(48, 8, b"a"),
+                image(32, 32, b"b"),
+                image(48, 32, b"c"),
+                image(48, 32, b"d"),
+            ],
+        };
+        assert_eq!(group.largest().unwrap().data, b"c");
+        assert!(IconGroup { images: vec![] }.largest().is_none());
+    }
+
+    #[test]
+    fn group_entries_without_their_image_are_skipped() {
+        let mut file = pe_with_icons(&[(16, 8, b"small"), (32, 8, b"large")]);
+        // Point the second group entry to icon 9.
+        let id = file.len() - 2;
+        file[id..].copy_from_slice(&9u16.to_le_bytes());
+        let groups = icon_groups(&file).unwrap();
+        assert_eq!(groups[0].images.len(), 1);
+        assert_eq!(groups[0].images[0].data, b"small");
+    }
+
+    #[test]
+    fn damaged_files_are_errors() {
+        assert_eq!(
+            icon_groups(b"ELF").err().as_deref(),
+            Some("not a Windows executable")
+        );
+        let file = pe_with_icons(&[(16, 8, b"small")]);
+        for cut in [0x50, 0x150, file.len() - 20] {
+            assert!(icon_groups(&file[..cut]).is_err(), "cut at 0x{:x}", cut);
+        }
+        let mut no_resources = file.clone();
+        no_resources[0x58 + 96 + 16..0x58 + 96 + 20].fill(0);
+        assert!(icon_groups(&no_resources).unwrap().is_empty());
+    }
+}
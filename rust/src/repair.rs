@@ -0,0 +1,249 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use std::fmt::Display;
+
+use crate::shortcuts::{Shortcut, ShortcutProp, Shortcuts};
+use crate::vdf::TYPE_MAP;
+
+pub enum RepairNote {
+    Recovered {
+        index: String,
+        name: String,
+        offset: usize,
+    },
+    Lost {
+        index: String,
+        name: Option<String>,
+        offset: usize,
+        reason: String,
+    },
+    /// Bytes that are not part of any recovered entry.
+    Skipped { offset: usize, len: usize },
+}
+
+impl Display for RepairNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepairNote::Recovered {
+                index,
+                name,
+                offset,
+            } => write!(
+                f,
+                "+ entry \"{}\" {:?} recovered at 0x{:x}",
+                index, name, offset
+            ),
+            RepairNote::Lost {
+                index,
+                name,
+                offset,
+                reason,
+            } => {
+                write!(f, "- entry \"{}\"", index)?;
+                if let Some(name) = name {
+                    write!(f, " (probably {:?})", name)?;
+                }
+                write!(f, " lost at 0x{:x}: {}", offset, reason)
+            }
+            RepairNote::Skipped { offset, len } => write!(
+                f,
+                "! {} unreadable bytes skipped at 0x{:x}-0x{:x}",
+                len,
+                offset,
+                offset + len
+            ),
+        }
+    }
+}
+
+pub struct RepairReport {
+    pub shortcuts: Shortcuts,
+    pub notes: Vec<RepairNote>,
+}
+
+/// `00 "<digits>" 00` opening an entry map at `offset`. Returns the index
+/// and the offset of the first item of the map.
+fn entry_start(buffer: &[u8], offset: usize) -> Option<(String, usize)> {
+    if buffer.get(offset) != Some(&TYPE_MAP) {
+        return None;
+    }
+    let digits = buffer[offset + 1..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    if digits == 0 || buffer.get(offset + 1 + digits) != Some(&0) {
+        return None;
+    }
+    let index = String::from_utf8_lossy(&buffer[offset + 1..offset + 1 + digits]).into_owned();
+    Some((index, offset + digits + 2))
+}
+
+/// Maps with an app name or an exe are shortcuts, anything else is a false
+/// positive of the scan.
+fn looks_like_shortcut(sc: &Shortcut) -> bool {
+    ["app_name", "exe"].iter().any(|k| {
+        matches!(
+            sc.props.get(*k),
+            Some(ShortcutProp::String(_) | ShortcutProp::Bytes(_))
+        )
+    })
+}
+
+/// Best effort name of a damaged entry, to tell the user what was lost.
+fn guess_name(buffer: &[u8], from: usize, to: usize) -> Option<String> {
+    let region = &buffer[from..to];
+    let key = b"appname\0";
+    let at = region
+        .windows(key.len())
+        .position(|w| w.eq_ignore_ascii_case(key))?;
+    let value = &region[at + key.len()..];
+    let end = value.iter().position(|b| *b == 0).unwrap_or(value.len());
+    Some(String::from_utf8_lossy(&value[..end]).into_owned())
+}
+
+/// Scan `buffer` for every complete entry map, whatever happened around it.
+/// Entries are read like `Shortcuts::parse` does, so strings that are not
+/// UTF-8 are kept as they are. Recovered entries keep their order and are
+/// renumbered from 0.
+pub fn repair(buffer: &[u8]) -> RepairReport {
+    let mut shortcuts = Shortcuts::empty();
+    let mut notes = vec![];
+    let mut pos = 0;
+    let mut skipped_from: Option<usize> = None;
+
+    // Header, end bytes and the gap between entries are expected.
+    let header = b"\0shortcuts\0";
+    if buffer.len() >= header.len() && buffer[..header.len()].eq_ignore_ascii_case(header) {
+        pos = header.len();
+    }
+
+    while pos < buffer.len() {
+        let Some((index, items_offset)) = entry_start(buffer, pos) else {
+            if buffer[pos] != 0x08 && skipped_from.is_none() {
+                skipped_from = Some(pos);
+            }
+            pos += 1;
+            continue;
+        };
+        let mut end = items_offset;
+        let parsed = Shortcut::parse(buffer, &mut end, 0).and_then(|sc| {
+            if !looks_like_shortcut(&sc) {
+                return Ok(None);
+            }
+            // A missing end byte makes the next entries look nested in this
+            // one, as lists keyed by digits.
+            if let Some((k, _)) = sc.props.iter().find(|(k, v)| {
+                matches!(v, ShortcutProp::Strings(_)) && k.bytes().all(|b| b.is_ascii_digit())
+            }) {
+                return Err(format!("end of entry missing before entry \"{}\"", k));
+            }
+            Ok(Some((sc, end)))
+        });
+
+        match parsed {
+            Ok(Some((sc, end))) => {
+                if let Some(from) = skipped_from.take() {
+                    notes.push(RepairNote::Skipped {
+                        offset: from,
+                        len: pos - from,
+                    });
+                }
+                notes.push(RepairNote::Recovered {
+                    index,
+                    name: sc.prop_to_string("app_name").unwrap_or_default(),
+                    offset: pos,
+                });
+                shortcuts.push(sc);
+                pos = end;
+            }
+            Err(reason) => {
+                if let Some(from) = skipped_from.take() {
+                    notes.push(RepairNote::Skipped {
+                        offset: from,
+                        len: pos - from,
+                    });
+                }
+                // Everything up to the next entry belongs to the damaged one.
+                let next = (pos + 1..buffer.len())
+                    .find(|o| entry_start(buffer, *o).is_some())
+                    .unwrap_or(buffer.len());
+                notes.push(RepairNote::Lost {
+                    index,
+                    name: guess_name(buffer, pos, next),
+                    offset: pos,
+                    reason,
+                });
+                pos = next;
+            }
+            Ok(None) => {
+                if skipped_from.is_none() {
+                    skipped_from = Some(pos);
+                }
+                pos += 1;
+            }
+        }
+    }
+    if let Some(from) = skipped_from {
+        notes.push(RepairNote::Skipped {
+            offset: from,
+            len: buffer.len() - from,
+        });
+    }
+
+    RepairReport { shortcuts, notes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NON_UTF8: &[u8] = include_bytes!("../tests/corpus/non_utf8.vdf");
+
+    fn store(scs: &Shortcuts) -> Vec<u8> {
+        let mut out = vec![];
+        scs.store_into(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn non_utf8_entries_are_recovered_as_they_are() {
+        let report = repair(NON_UTF8);
+        assert!(report
+            .notes
+            .iter()
+            .all(|n| matches!(n, RepairNote::Recovered { .. })));
+        let expected = Shortcuts::parse(NON_UTF8, &mut 0).unwrap();
+        assert_eq!(store(&report.shortcuts), store(&expected));
+        assert_eq!(
+            report.shortcuts.at(&0).unwrap().props.get("app_name"),
+            Some(&ShortcutProp::Bytes(b"Caf\xe9 Racer".to_vec()))
+        );
+    }
+
+    #[test]
+    fn entries_after_a_damaged_one_are_recovered() {
+        // Entry 0 loses its end byte, entry 1 is intact.
+        let mut scs = Shortcuts::parse(NON_UTF8, &mut 0).unwrap();
+        let copy = Shortcut::parse(NON_UTF8, &mut 14, 0).unwrap();
+        scs.push(copy);
+        let mut bytes = store(&scs);
+        let second = bytes.windows(3).rposition(|w| w == b"\x001\x00").unwrap();
+        bytes.remove(second - 1);
+
+        let report = repair(&bytes);
+        assert_eq!(report.shortcuts.len(), 1);
+        assert!(matches!(report.notes[0], RepairNote::Lost { .. }));
+        assert_eq!(
+            report.shortcuts.at(&0).unwrap().props.get("app_name"),
+            Some(&ShortcutProp::Bytes(b"Caf\xe9 Racer".to_vec()))
+        );
+    }
+}
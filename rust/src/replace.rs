@@ -0,0 +1,226 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use std::path::Path;
+
+use regex::{bytes, NoExpand, Regex};
+
+use crate::lint;
+use crate::shortcuts::{ShortcutProp, Shortcuts, SHORTCUT_PROP_INFO};
+
+/// Fields holding a path, the ones a moved games folder changes.
+pub const PATH_FIELDS: [&str; 4] = ["exe", "start_dir", "icon", "shortcut_path"];
+
+/// Replace every match of a pattern in string props. Raw byte props (not
+/// UTF-8) are matched as bytes, so they are never rewritten lossily.
+pub struct Replacer {
+    text: Regex,
+    bytes: bytes::Regex,
+    replacement: String,
+    /// `$1`, `${name}` in `replacement` are expanded for regex patterns only.
+    expand: bool,
+}
+
+impl Replacer {
+    pub fn new(pattern: &str, replacement: &str, is_regex: bool) -> Result<Self, String> {
+        let pattern = if is_regex {
+            pattern.to_owned()
+        } else {
+            regex::escape(pattern)
+        };
+        Ok(Self {
+            text: Regex::new(&pattern).map_err(|e| e.to_string())?,
+            bytes: bytes::Regex::new(&pattern).map_err(|e| e.to_string())?,
+            replacement: replacement.to_owned(),
+            expand: is_regex,
+        })
+    }
+
+    /// New value of `prop`, `None` when nothing matches.
+    pub fn apply(&self, prop: &ShortcutProp) -> Option<ShortcutProp> {
+        match prop {
+            ShortcutProp::String(s) if self.text.is_match(s) => {
+                Some(ShortcutProp::String(if self.expand {
+                    self.text
+                        .replace_all(s, self.replacement.as_str())
+                        .into_owned()
+                } else {
+                    self.text
+                        .replace_all(s, NoExpand(&self.replacement))
+                        .into_owned()
+                }))
+            }
+            ShortcutProp::Bytes(b) if self.bytes.is_match(b) => {
+                let replaced = if self.expand {
+                    self.bytes
+                        .replace_all(b, self.replacement.as_bytes())
+                        .into_owned()
+                } else {
+                    self.bytes
+                        .replace_all(b, bytes::NoExpand(self.replacement.as_bytes()))
+                        .into_owned()
+                };
+                Some(match String::from_utf8(replaced) {
+                    Ok(s) => ShortcutProp::String(s),
+                    Err(e) => ShortcutProp::Bytes(e.into_bytes()),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Check that every name of `fields` is a known string field.
+pub fn check_fields(fields: &[String]) -> Result<(), String> {
+    for field in fields {
+        let known = SHORTCUT_PROP_INFO
+            .iter()
+            .any(|p| p.switchname == field && matches!(p.type_default, ShortcutProp::String(_)));
+        if !known {
+            return Err(format!(
+                "\"{}\" is not a string field. Known ones are: {}",
+                field,
+                SHORTCUT_PROP_INFO
+                    .iter()
+                    .filter(|p| matches!(p.type_default, ShortcutProp::String(_)))
+                    .map(|p| p.switchname)
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Apply `replacer` to `fields` of the entries at `indices`, or of every
+/// entry when `indices` is empty. Returns index and field of every change.
+pub fn replace(
+    scs: &mut Shortcuts,
+    indices: &[u32],
+    fields: &[String],
+    replacer: &Replacer,
+) -> Vec<(u32, String)> {
+    let selected: Vec<u32> = scs
+        .iter()
+        .filter_map(|sc| sc.prop_to_u32("index"))
+        .filter(|i| indices.is_empty() || indices.contains(i))
+        .collect();
+    let mut changed = vec![];
+    for index in selected {
+        let Some(sc) = scs.at_mut(&index) else {
+            continue;
+        };
+        for field in fields {
+            if let Some(value) = sc.props.get(field).and_then(|p| replacer.apply(p)) {
+                sc.props.insert(field.clone(), value);
+                changed.push((index, field.clone()));
+            }
+        }
+    }
+    changed
+}
+
+/// The `changed` fields whose new value is a path that does not exist.
+/// Empty values and fields that hold no path are not checked.
+pub fn missing_paths(scs: &Shortcuts, changed: &[(u32, String)]) -> Vec<String> {
+    changed
+        .iter()
+        .filter(|(_, field)| PATH_FIELDS.contains(&field.as_str()))
+        .filter_map(|(index, field)| {
+            let sc = scs.at(index)?;
+            let value = sc.props.get(field)?.text().into_owned();
+            let (path, _) = lint::split_path(&value)?;
+            let exists = match field.as_str() {
+                "exe" => lint::resolve_exe(path).exists(),
+                _ => Path::new(path).exists(),
+            };
+            (!path.is_empty() && !exists)
+                .then(|| format!("[{}] {}: {:?} does not exist", index, field, path))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortcuts::Shortcut;
+
+    #[test]
+    fn literal_patterns_are_not_regexes() {
+        let replacer = Replacer::new("/games (old)/", "/games/$1/", false).unwrap();
+        assert_eq!(
+            replacer.apply(&ShortcutProp::string("\"/games (old)/a.exe\"")),
+            Some(ShortcutProp::string("\"/games/$1/a.exe\""))
+        );
+        assert_eq!(
+            replacer.apply(&ShortcutProp::string("/games/old/a.exe")),
+            None
+        );
+    }
+
+    #[test]
+    fn regex_replacements_expand_groups() {
+        let replacer = Replacer::new(r"/mnt/(\w+)/", "/media/$1/", true).unwrap();
+        assert_eq!(
+            replacer.apply(&ShortcutProp::string("/mnt/ssd/a /mnt/hdd/b")),
+            Some(ShortcutProp::string("/media/ssd/a /media/hdd/b"))
+        );
+        assert!(Replacer::new("(", "", true).is_err());
+    }
+
+    #[test]
+    fn bytes_are_matched_as_bytes() {
+        let replacer = Replacer::new("/old/", "/new/", false).unwrap();
+        assert_eq!(
+            replacer.apply(&ShortcutProp::Bytes(b"/old/caf\xe9.exe".to_vec())),
+            Some(ShortcutProp::Bytes(b"/new/caf\xe9.exe".to_vec()))
+        );
+        assert_eq!(replacer.apply(&ShortcutProp::Bytes(b"\xe9".to_vec())), None);
+        assert_eq!(replacer.apply(&ShortcutProp::UInt32(0)), None);
+    }
+
+    #[test]
+    fn only_string_fields_are_accepted() {
+        assert!(check_fields(&["exe".to_owned(), "icon".to_owned()]).is_ok());
+        let error = check_fields(&["tags".to_owned()]).unwrap_err();
+        assert!(
+            error.starts_with("\"tags\" is not a string field."),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn replace_reports_changes_of_the_selected_entries() {
+        let mut scs =
+            Shortcuts::from_entries(["/old/a.exe", "/old/b.exe", "/other/c.exe"].map(|exe| {
+                Shortcut::empty()
+                    .with_string("exe", exe)
+                    .with_string("start_dir", "/old/")
+            }));
+        let replacer = Replacer::new("/old/", "/nowhere/", false).unwrap();
+        let fields = ["exe".to_owned()];
+        let changed = replace(&mut scs, &[1, 2], &fields, &replacer);
+        assert_eq!(changed, [(1, "exe".to_owned())]);
+        assert_eq!(
+            scs.at(&0).unwrap().prop_to_string("exe").unwrap(),
+            "/old/a.exe"
+        );
+        assert_eq!(
+            scs.at(&1).unwrap().prop_to_string("start_dir").unwrap(),
+            "/old/"
+        );
+
+        assert_eq!(
+            missing_paths(&scs, &changed),
+            ["[1] exe: \"/nowhere/b.exe\" does not exist"]
+        );
+    }
+}
@@ -8,25 +8,74 @@
  * @author: Magius(CHE) - magiusche@magius.it
  */
 
-use std::{collections::HashMap, fs::File, io::Write, str::from_utf8};
+use std::{borrow::Cow, cmp::Ordering, collections::HashMap, io::Write};
 
-#[derive(Debug)]
+use crate::columns::Column;
+use crate::vdf::{self, MAX_DEPTH};
+
+#[derive(Debug, Default, Clone, PartialEq)]
 pub enum ShortcutProp {
     UInt32(u32),
     String(String),
+    /// String that is not valid UTF-8, kept as read so it is written back
+    /// unchanged. Shown with invalid sequences replaced.
+    Bytes(Vec<u8>),
     Strings(Vec<String>),
+    #[default]
     None,
 }
 
-impl Default for ShortcutProp {
+impl Default for &ShortcutProp {
     fn default() -> Self {
-        Self::None
+        &ShortcutProp::None
     }
 }
 
-impl Default for &ShortcutProp {
-    fn default() -> Self {
-        &ShortcutProp::None
+impl ShortcutProp {
+    /// Order two values for sorting. Strings compare case-insensitively first,
+    /// `None` sorts before everything else.
+    pub fn compare(&self, other: &ShortcutProp) -> Ordering {
+        match (self, other) {
+            (ShortcutProp::UInt32(a), ShortcutProp::UInt32(b)) => a.cmp(b),
+            (ShortcutProp::Strings(a), ShortcutProp::Strings(b)) => a.cmp(b),
+            (a, b) if a.rank() == 2 && b.rank() == 2 => {
+                let (a, b) = (a.text(), b.text());
+                a.to_lowercase()
+                    .cmp(&b.to_lowercase())
+                    .then_with(|| a.cmp(&b))
+            }
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+
+    /// Text of a string prop, lossy for `Bytes`. Empty for other kinds.
+    pub fn text(&self) -> Cow<'_, str> {
+        match self {
+            ShortcutProp::String(s) => Cow::Borrowed(s),
+            ShortcutProp::Bytes(b) => String::from_utf8_lossy(b),
+            _ => Cow::Borrowed(""),
+        }
+    }
+
+    /// JSON value of the prop, `None` for a missing one.
+    pub fn to_json(&self) -> Option<serde_json::Value> {
+        match self {
+            ShortcutProp::UInt32(n) => Some(serde_json::Value::from(*n)),
+            ShortcutProp::String(_) | ShortcutProp::Bytes(_) => {
+                Some(serde_json::Value::from(self.text()))
+            }
+            ShortcutProp::Strings(arr) => Some(serde_json::Value::from(vdf::lossy_all(arr))),
+            ShortcutProp::None => None,
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            ShortcutProp::None => 0,
+            ShortcutProp::UInt32(_) => 1,
+            ShortcutProp::String(_) | ShortcutProp::Bytes(_) => 2,
+            ShortcutProp::Strings(_) => 3,
+        }
     }
 }
 
@@ -49,6 +98,7 @@ impl TryFrom<&ShortcutProp> for String {
     fn try_from(u: &ShortcutProp) -> Result<Self, Self::Error> {
         match u {
             ShortcutProp::String(n) => Ok(n.clone()),
+            ShortcutProp::Bytes(_) => Ok(u.text().into_owned()),
             _ => Err(format!(
                 "Value out of range. Expected ShortcutProp::String(n) but got {:?}",
                 u
@@ -61,7 +111,7 @@ impl TryFrom<&ShortcutProp> for Vec<String> {
 
     fn try_from(u: &ShortcutProp) -> Result<Self, Self::Error> {
         match u {
-            ShortcutProp::Strings(n) => Ok(n.iter().map(|s| s.clone()).collect()),
+            ShortcutProp::Strings(n) => Ok(n.to_vec()),
             _ => Err(format!(
                 "Value out of range. Expected ShortcutProp::Strings(n) but got {:?}",
                 u
@@ -191,125 +241,7 @@ impl TryFrom<u8> for VdfMapItemType {
     }
 }
 
-pub type VdfMap = HashMap<String, Value>;
-
-#[derive(Debug)]
-pub enum Value {
-    String(String),
-    UInt32(u32),
-    Map(VdfMap),
-}
-
-impl TryFrom<&Value> for u32 {
-    type Error = String;
-
-    fn try_from(value: &Value) -> Result<Self, Self::Error> {
-        //println!("Convert from {:?}", value);
-        match value {
-            Value::UInt32(u) => Ok(*u),
-            _ => Err(format!("Cannot convert non-UInt32 {:?} into u32", value)),
-        }
-    }
-}
-
-impl TryFrom<&Value> for String {
-    type Error = String;
-
-    fn try_from(value: &Value) -> Result<Self, Self::Error> {
-        //println!("Convert from {:?}", value);
-        match value {
-            Value::String(u) => Ok(u.clone()),
-            _ => Err(format!("Cannot convert non-String {:?} into String", value)),
-        }
-    }
-}
-
-// impl TryFrom<&Value> for Shortcut {
-//     type Error = String;
-
-//     fn try_from(value: &Value) -> Result<Self, Self::Error> {
-//         match value {
-//             Value::Map(u) => Ok(
-//                 TryInto::<Shortcut>::try_into(u).unwrap()),
-//             _ => Err(format!("Cannot convert non-Map {:?} into Shortcut", value)),
-//         }
-//     }
-// }
-
-impl TryFrom<&Value> for HashMap<u32, Shortcut> {
-    type Error = String;
-
-    fn try_from(value: &Value) -> Result<Self, Self::Error> {
-        match value {
-            Value::Map(u) => Ok(u
-                .iter()
-                .map(|(k, v)| match v {
-                    Value::Map(s) => (
-                        k.parse().unwrap(),
-                        TryInto::<Shortcut>::try_into((k.parse().unwrap(), s)).unwrap(),
-                    ),
-                    _ => todo!(),
-                })
-                .collect()),
-            _ => Err(format!(
-                "Cannot convert non-Map {:?} into HashMap<u32, Shortcut>",
-                value
-            )),
-        }
-    }
-}
-
-impl TryFrom<(u32, &VdfMap)> for Shortcut {
-    type Error = String;
-
-    fn try_from((index, map): (u32, &VdfMap)) -> Result<Self, Self::Error> {
-        let mut props: HashMap<String, ShortcutProp> = map
-            .iter()
-            .map(|(k, v)| {
-                (
-                    // here nees switchanme (and we have vdfname lowercase)
-                    String::from(
-                        SHORTCUT_PROP_INFO
-                            .iter()
-                            .find(|e| e.name == k)
-                            .unwrap()
-                            .switchname,
-                    ),
-                    match v {
-                        Value::String(s) => ShortcutProp::String(s.clone()),
-                        Value::UInt32(u) => ShortcutProp::UInt32(*u),
-                        Value::Map(_) => {
-                            ShortcutProp::Strings(TryInto::<Vec<String>>::try_into(v).unwrap())
-                        }
-                    },
-                )
-            })
-            .collect();
-        props.insert("index".to_owned(), ShortcutProp::UInt32(index));
-        Ok(Self { props })
-    }
-}
-
-impl TryFrom<&Value> for Vec<String> {
-    type Error = String;
-
-    fn try_from(value: &Value) -> Result<Self, Self::Error> {
-        //println!("Convert from {:?}", value);
-
-        match value {
-            Value::Map(u) => Ok(u
-                .values()
-                .map(|v| TryInto::<String>::try_into(v).unwrap_or("".to_owned()))
-                .collect()),
-            _ => Err(format!(
-                "Cannot convert non-Map {:?} into Vec<String>",
-                value
-            )),
-        }
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Shortcuts {
     shortcuts: HashMap<u32, Shortcut>,
 }
@@ -333,25 +265,26 @@ pub struct Shortcuts {
 // }
 
 impl Shortcuts {
+    /// `parse`, without the reason of a failure.
     pub fn from(buffer: &[u8], index: &mut usize) -> Option<Self> {
-        if let Some(mut shortcuts) = consume_map(buffer, index) {
-            //println!("Next word is {:?}", appid_label);
-            //println!("[{index:x}] add shortcut {:?}", &value);
-
-            if !shortcuts.contains_key("shortcuts") {
-                println!("Missing header \"shortcuts\"");
-                return None;
-            }
+        Self::parse(buffer, index).ok()
+    }
 
-            if let Some(shortcuts_val) = shortcuts.remove("shortcuts") {
-                return Some(Shortcuts {
-                    shortcuts: TryInto::<HashMap<u32, Shortcut>>::try_into(&shortcuts_val).unwrap(),
-                });
-            } else {
-                return None;
+    /// Read the root map of a binary vdf, keeping its "shortcuts" map only.
+    /// Values are read straight from `buffer` into the shortcut props.
+    pub fn parse(buffer: &[u8], index: &mut usize) -> Result<Self, String> {
+        let mut shortcuts = None;
+        while let Some((item_type, key)) = consume_item_header(buffer, index)? {
+            match item_type {
+                VdfMapItemType::Map if key.eq_ignore_ascii_case(b"shortcuts") => {
+                    shortcuts = Some(consume_entries(buffer, index)?)
+                }
+                other => skip_value(buffer, index, other, 0)?,
             }
         }
-        None
+        shortcuts
+            .map(|shortcuts| Shortcuts { shortcuts })
+            .ok_or("Missing header \"shortcuts\"".to_owned())
     }
     pub fn at(&self, index: &u32) -> Option<&Shortcut> {
         self.shortcuts.get(index)
@@ -369,14 +302,58 @@ impl Shortcuts {
             let mut sc = Shortcut::empty();
             new = true;
             fun(new, &mut sc)?;
+            sc.props
+                .insert("index".to_owned(), ShortcutProp::UInt32(*index));
             self.shortcuts.insert(*index, sc);
         }
 
         Ok(new)
     }
+
+    /// Index a new entry appended to the list would get.
+    pub fn next_index(&self) -> u32 {
+        self.shortcuts.keys().max().map_or(0, |m| m + 1)
+    }
     pub fn len(&self) -> usize {
         self.shortcuts.len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.shortcuts.is_empty()
+    }
+
+    pub fn at_mut(&mut self, index: &u32) -> Option<&mut Shortcut> {
+        self.shortcuts.get_mut(index)
+    }
+
+    /// Append `sc` to the end of the list and return its index.
+    pub fn push(&mut self, mut sc: Shortcut) -> u32 {
+        let index = self.next_index();
+        sc.props
+            .insert("index".to_owned(), ShortcutProp::UInt32(index));
+        self.shortcuts.insert(index, sc);
+        index
+    }
+
+    /// Remove an entry. Indices of the other entries are left untouched, call
+    /// `renumber` once done.
+    pub fn remove(&mut self, index: &u32) -> Option<Shortcut> {
+        self.shortcuts.remove(index)
+    }
+
+    /// Make indices contiguous again, keeping the current order.
+    pub fn renumber(&mut self) {
+        let mut indices: Vec<u32> = self.shortcuts.keys().copied().collect();
+        indices.sort();
+        let mut renumbered = HashMap::new();
+        for (n, old) in indices.iter().enumerate() {
+            if let Some(mut sc) = self.shortcuts.remove(old) {
+                sc.props
+                    .insert("index".to_owned(), ShortcutProp::UInt32(n as u32));
+                renumbered.insert(n as u32, sc);
+            }
+        }
+        self.shortcuts = renumbered;
+    }
 
     pub fn empty() -> Self {
         Self {
@@ -384,89 +361,98 @@ impl Shortcuts {
         }
     }
 
-    pub fn store_into(&self, file: &mut File) -> Result<(), String> {
+    /// See `Shortcut::decode_bytes`.
+    pub fn decode_bytes(&mut self, decode: &dyn Fn(&[u8]) -> String) {
+        for sc in self.shortcuts.values_mut() {
+            sc.decode_bytes(decode);
+        }
+    }
+
+    /// Write the binary vdf. `file` should be buffered, it gets many small writes.
+    pub fn store_into(&self, file: &mut impl Write) -> Result<(), String> {
         write_type(file, VdfMapItemType::Map)?;
         write_string(file, "shortcuts")?;
-        for (_, sc) in &self.shortcuts {
-            sc.write_into(file)?;
+        let mut indices: Vec<&u32> = self.shortcuts.keys().collect();
+        indices.sort();
+        for index in indices {
+            self.shortcuts[index].write_into(file)?;
         }
         write_type(file, VdfMapItemType::MapEnd)?;
         write_type(file, VdfMapItemType::MapEnd)?;
-        Ok(())
+        file.flush()
+            .map_err(|err| format!("Error while writing file. {:?}", err))
     }
 
-    pub fn update_from_json(&mut self, jsonstring: &String) -> Result<(), String> {
-        match serde_json::from_str::<serde_json::Value>(jsonstring.as_str()) {
-            Ok(json) => {
-                if let serde_json::Value::Array(a) = json {
-                    for (i, v) in a.iter().enumerate() {
-                        if let serde_json::Value::Object(s) = v {
-                            if let Ok(idx) = from_json_number(&s["index"]) {
-                                self.at_or_new(
-                                    &idx,
-                                    |_, sc: &mut Shortcut| -> Result<(), String> {
-                                        for prop in SHORTCUT_PROP_INFO.iter() {
-                                            match &prop.type_default {
-                                                ShortcutProp::UInt32(_) => {
-                                                    if let Some(val) = s.get(prop.switchname) {
-                                                        *sc.props
-                                                            .entry(String::from(prop.switchname))
-                                                            .or_default() = ShortcutProp::UInt32(
-                                                            from_json_number(val)?,
-                                                        )
-                                                    }
-                                                }
-                                                ShortcutProp::String(_) => {
-                                                    if let Some(val) = s.get(prop.switchname) {
-                                                        *sc.props
-                                                            .entry(String::from(prop.switchname))
-                                                            .or_default() = ShortcutProp::String(
-                                                            from_json_string(val)?,
-                                                        );
-                                                    }
-                                                    //println!("Analyze {:?} {:?}", prop.switchname, &s);
-                                                    //let h = sc.props.entry(String::from(prop.switchname)).or_default();
-                                                    //*h = ShortcutProp::String(from_json_string(&s[prop.switchname])?);
-                                                }
-                                                ShortcutProp::Strings(_) => {
-                                                    if let Some(val) = s.get(prop.switchname) {
-                                                        *sc.props
-                                                            .entry(String::from(prop.switchname))
-                                                            .or_default() = ShortcutProp::Strings(
-                                                            from_json_array(val)?,
-                                                        )
-                                                    }
-                                                }
-                                                ShortcutProp::None => unreachable!(),
-                                            }
-                                        }
-                                        Ok(())
-                                    },
-                                )?;
-                            } else {
-                                return Err(format!("Missing \"index\" at object[{}]", i));
-                            }
-                        } else {
-                            return Err(
-                                "Input must a be a valid json array made with objects.".to_owned()
-                            );
-                        }
-                    }
-                    Ok(())
+    /// Update or add entries from json objects. Every object must carry the
+    /// "index" of the entry it refers to; unknown keys are ignored.
+    pub fn update_from_values(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        for (i, v) in values.iter().enumerate() {
+            if let serde_json::Value::Object(s) = v {
+                if let Some(Ok(idx)) = s.get("index").map(from_json_number) {
+                    self.at_or_new(&idx, |_, sc: &mut Shortcut| -> Result<(), String> {
+                        sc.update_from_object(s)
+                    })?;
                 } else {
-                    Err("Input must a be a valid json array.".to_owned())
+                    return Err(format!("Missing \"index\" at object[{}]", i));
+                }
+            } else {
+                return Err("Input must a be a valid json array made with objects.".to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    /// Update or add entries from the records of a CSV, TSV, NDJSON, YAML or
+    /// TOML file. Like `update_from_values`, a record with an "index" updates
+    /// that entry or creates it there; records without one are appended to
+    /// the end of the list. Unknown keys are kept as extra props.
+    pub fn import_records(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        for (i, v) in values.iter().enumerate() {
+            let serde_json::Value::Object(s) = v else {
+                return Err(format!("Record {} is not an object.", i + 1));
+            };
+            match record_object(s).get("index") {
+                Some(idx) => {
+                    self.at_or_new(&from_json_number(idx)?, |_, sc: &mut Shortcut| {
+                        sc.update_from_record(s)
+                    })?;
+                }
+                None => {
+                    let mut sc = Shortcut::empty();
+                    sc.update_from_record(s)?;
+                    self.push(sc);
                 }
             }
-            Err(err) => Err(format!("{}", err)),
         }
+        Ok(())
     }
 }
 
+/// A record with its keys as switch names. Known fields may also be named
+/// like `--columns` accepts them (`appname`, `AppName`), other keys are
+/// lowercased and derived columns dropped. Booleans, as YAML and TOML write
+/// flags, become 0 or 1.
+fn record_object(
+    s: &serde_json::Map<String, serde_json::Value>,
+) -> serde_json::Map<String, serde_json::Value> {
+    s.iter()
+        .filter_map(|(key, val)| {
+            let key = match Column::parse(key) {
+                Some(Column::Prop(p)) => p.switchname.to_owned(),
+                Some(_) => return None,
+                None => key.to_lowercase(),
+            };
+            let val = match val {
+                serde_json::Value::Bool(b) => serde_json::Value::from(*b as u32),
+                _ => val.clone(),
+            };
+            Some((key, val))
+        })
+        .collect()
+}
+
 fn from_json_number(jn: &serde_json::Value) -> Result<u32, String> {
-    if let Some(num) = jn
-        .as_u64()
-        .and_then(|e| u32::try_from(e).map_or(None, |e| Some(e)))
-    {
+    if let Some(num) = jn.as_u64().and_then(|e| u32::try_from(e).ok()) {
         Ok(num)
     } else {
         Err(format!(
@@ -476,7 +462,7 @@ fn from_json_number(jn: &serde_json::Value) -> Result<u32, String> {
     }
 }
 fn from_json_string(jn: &serde_json::Value) -> Result<String, String> {
-    if let Some(s) = jn.as_str().and_then(|e| Some(String::from(e))) {
+    if let Some(s) = jn.as_str().map(String::from) {
         Ok(s)
     } else {
         Err(format!(
@@ -507,21 +493,43 @@ fn from_json_array(jn: &serde_json::Value) -> Result<Vec<String>, String> {
     }
 }
 
-fn write_string(file: &mut File, string: &str) -> Result<(), String> {
-    match write!(file, "{}\0", string) {
-        Err(err) => Err(format!("Error while writing string {}. {:?}", string, err)),
+fn write_string(file: &mut impl Write, string: &str) -> Result<(), String> {
+    write_bytes(file, string.as_bytes())
+}
+
+/// `write_string` of a tag or key read by `vdf::string_from_bytes`, whose
+/// bytes may not be UTF-8.
+fn write_raw_string(file: &mut impl Write, string: &str) -> Result<(), String> {
+    write_bytes(file, &vdf::string_to_bytes(string))
+}
+
+/// NUL terminated string. An inner NUL would end it early and corrupt the
+/// rest of the file, so it is refused.
+fn write_bytes(file: &mut impl Write, bytes: &[u8]) -> Result<(), String> {
+    if memchr::memchr(0, bytes).is_some() {
+        return Err(format!(
+            "String {:?} contains a NUL character.",
+            String::from_utf8_lossy(bytes)
+        ));
+    }
+    match file.write_all(bytes).and_then(|_| file.write_all(&[0])) {
+        Err(err) => Err(format!(
+            "Error while writing string {}. {:?}",
+            String::from_utf8_lossy(bytes),
+            err
+        )),
         _ => Ok(()),
     }
 }
 
-fn write_u32(file: &mut File, num: &u32) -> Result<(), String> {
+fn write_u32(file: &mut impl Write, num: &u32) -> Result<(), String> {
     match file.write_u32::<LittleEndian>(*num) {
         Err(err) => Err(format!("Error while writing u32 {}. {:?}", num, err)),
         _ => Ok(()),
     }
 }
 
-fn write_type(file: &mut File, mtype: VdfMapItemType) -> Result<(), String> {
+fn write_type(file: &mut impl Write, mtype: VdfMapItemType) -> Result<(), String> {
     match file.write_all(&[mtype as u8]) {
         Err(err) =>
         /*Err(format!("Error while writing byte {:?}. {:?}", &mtype, err))*/
@@ -532,205 +540,176 @@ fn write_type(file: &mut File, mtype: VdfMapItemType) -> Result<(), String> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Shortcut {
     pub props: HashMap<String, ShortcutProp>,
 }
 
 impl Shortcuts {
-    pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a Shortcut> + '_ {
+    /// Entries in index order. Indices may have gaps, e.g. in files edited by
+    /// other tools.
+    pub fn iter(&self) -> impl Iterator<Item = &Shortcut> + '_ {
+        let mut indices: Vec<u32> = self.shortcuts.keys().copied().collect();
+        indices.sort();
         ShortcutIter {
             shortcuts: self,
-            index: None,
-            size: self.len(),
+            indices: indices.into_iter(),
         }
     }
 }
 
 pub struct ShortcutIter<'a> {
     shortcuts: &'a Shortcuts,
-    index: Option<usize>,
-    size: usize,
+    indices: std::vec::IntoIter<u32>,
 }
 
 impl<'a> Iterator for ShortcutIter<'a> {
     type Item = &'a Shortcut;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.index = Some(match self.index {
-            None => 0,
-            Some(n) => n + 1,
-        });
-        if let Some(act) = self.index {
-            //println!("Scan iterator {}",act);
-            if act >= self.size {
-                return None;
+        let index = self.indices.next()?;
+        self.shortcuts.at(&index)
+    }
+}
+
+fn eof(offset: usize, what: &str) -> String {
+    format!(
+        "Unexpected end of file at 0x{:x}, {} expected",
+        offset, what
+    )
+}
+
+/// Type and key of the next map item, `None` at the end of the map.
+fn consume_item_header<'a>(
+    buffer: &'a [u8],
+    index: &mut usize,
+) -> Result<Option<(VdfMapItemType, &'a [u8])>, String> {
+    let offset = *index;
+    let btype = consume_byte(buffer, index).ok_or_else(|| eof(offset, "type byte or map end"))?;
+    let item_type = VdfMapItemType::try_from(btype)
+        .map_err(|_| format!("Unsupported type byte 0x{:02x} at 0x{:x}", btype, offset))?;
+    if let VdfMapItemType::MapEnd = item_type {
+        return Ok(None);
+    }
+    let key = consume_slice(buffer, index).ok_or_else(|| eof(*index, "key"))?;
+    Ok(Some((item_type, key)))
+}
+
+/// Move past a value without reading it.
+fn skip_value(
+    buffer: &[u8],
+    index: &mut usize,
+    item_type: VdfMapItemType,
+    depth: usize,
+) -> Result<(), String> {
+    match item_type {
+        VdfMapItemType::String => consume_slice(buffer, index)
+            .map(|_| ())
+            .ok_or_else(|| eof(*index, "string value")),
+        VdfMapItemType::UInt32 => consume_u32(buffer, index)
+            .map(|_| ())
+            .ok_or_else(|| eof(*index, "int32 value")),
+        VdfMapItemType::Map if depth >= MAX_DEPTH => Err(format!(
+            "Maps nested deeper than {} levels at 0x{:x}",
+            MAX_DEPTH, *index
+        )),
+        VdfMapItemType::Map => {
+            while let Some((item_type, _)) = consume_item_header(buffer, index)? {
+                skip_value(buffer, index, item_type, depth + 1)?;
             }
-            return self.shortcuts.at(&(act as u32));
-        }
-        None
-    }
-}
-
-// impl TryFrom<(u32, &VdfMap)> for Shortcut {
-//     type Error = String;
-
-//     fn try_from((index, mainmap): (u32, &VdfMap)) -> Result<Self, Self::Error> {
-//         //println!("From {:?}", mainmap);
-//         let map;
-//         if let Some(Value::Map(submap)) = mainmap.get(format!("{}", index).as_str()) {
-//             map = submap
-//         } else {
-//             return Err(format!("Missing shortcut at {}", index));
-//         }
-
-//         let mut props: HashMap<String, ShortcutProp> = HashMap::new();
-
-//         props.insert("index".to_owned(), ShortcutProp::UInt32(index));
-
-//         SHORTCUT_PROP_INFO.iter().for_each(|info| {
-//             if info.name == "index" {
-//                 return;
-//             }
-//             let val = match &info.type_default {
-//                 ShortcutProp::UInt32(def) => ShortcutProp::UInt32(
-//                     TryInto::<u32>::try_into(map.get(info.name).unwrap_or(&Value::UInt32(*def)))
-//                         .unwrap(),
-//                 ),
-//                 ShortcutProp::String(def) => ShortcutProp::String(
-//                     TryInto::<String>::try_into(
-//                         map.get(info.name).unwrap_or(&Value::String(def.to_owned())),
-//                     )
-//                     .unwrap(),
-//                 ),
-//                 ShortcutProp::Strings(_) => ShortcutProp::Strings(
-//                     TryInto::<Vec<String>>::try_into(
-//                         map.get(info.name).unwrap_or(&Value::Map(HashMap::new())),
-//                     )
-//                     .unwrap(),
-//                 ),
-//                 _ => unreachable!(),
-//             };
-//             props.insert(info.switchname.to_owned(), val);
-//             //*props.entry(info.switchname).or_insert(val) = val;
-//         });
-
-//         Ok(Self { props })
-//     }
-// }
+            Ok(())
+        }
+        VdfMapItemType::MapEnd => Ok(()),
+    }
+}
 
-fn consume_map_item(buffer: &[u8], index: &mut usize) -> Option<(String, Value)> {
-    //println!("[{index:x}] consume_map_item");
-    let btype = consume_byte(buffer, index)?;
-    //println!("[{index:x}]  - type {:x}", btype);
-    let btypeinto = btype.try_into();
-    match btypeinto {
-        Err(_) => {
-            println!("[{index:x}]  - type {:x} is invalid.", btype);
-            return None;
+/// Entries of the "shortcuts" map, keyed by their index.
+fn consume_entries(buffer: &[u8], index: &mut usize) -> Result<HashMap<u32, Shortcut>, String> {
+    let mut shortcuts = HashMap::new();
+    while let Some((item_type, key)) = consume_item_header(buffer, index)? {
+        let key = String::from_utf8_lossy(key);
+        let n = key
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid shortcut index {:?}", key))?;
+        if !matches!(item_type, VdfMapItemType::Map) {
+            return Err(format!("Shortcut {:?} is not a map", key));
         }
-        Ok(VdfMapItemType::MapEnd) => return None,
-        _ => (),
-    };
+        shortcuts.insert(n, Shortcut::parse(buffer, index, n)?);
+    }
+    Ok(shortcuts)
+}
 
-    let name = consume_string(buffer, index)?;
-    //println!("[{index:x}]  - Key = {:?}", name);
-    match btypeinto {
-        Ok(VdfMapItemType::Map) => Some((name, Value::Map(consume_map(buffer, index)?))),
-        Ok(VdfMapItemType::String) => Some((name, Value::String(consume_string(buffer, index)?))),
-        Ok(VdfMapItemType::UInt32) => Some((name, Value::UInt32(consume_u32(buffer, index)?))),
-        _ => None,
+/// Items of a list map like "tags", keyed "0", "1", ...: keep their order,
+/// not the one they are stored in. Values that are not strings are empty.
+fn consume_strings(buffer: &[u8], index: &mut usize) -> Result<Vec<String>, String> {
+    let mut items: Vec<(u32, String)> = vec![];
+    while let Some((item_type, key)) = consume_item_header(buffer, index)? {
+        let n = std::str::from_utf8(key)
+            .ok()
+            .and_then(|k| k.parse::<u32>().ok())
+            .unwrap_or(u32::MAX);
+        let value = match item_type {
+            VdfMapItemType::String => vdf::string_from_bytes(
+                consume_slice(buffer, index).ok_or_else(|| eof(*index, "string value"))?,
+            ),
+            other => {
+                skip_value(buffer, index, other, 1)?;
+                String::new()
+            }
+        };
+        items.push((n, value));
     }
+    items.sort_by_key(|(n, _)| *n);
+    Ok(items.into_iter().map(|(_, v)| v).collect())
 }
 
-fn consume_map(buffer: &[u8], index: &mut usize) -> Option<VdfMap> {
-    let mut map = VdfMap::new();
-    while let Some((key, value)) = consume_map_item(buffer, index) {
-        map.insert(key.to_lowercase(), value);
+/// Switch name of a known vdf key, the lowercase key itself otherwise. Bytes
+/// that are not UTF-8 are kept, see `vdf::string_from_bytes`.
+fn prop_name(key: &[u8]) -> String {
+    match SHORTCUT_PROP_INFO
+        .iter()
+        .find(|e| e.name.as_bytes().eq_ignore_ascii_case(key))
+    {
+        Some(e) => String::from(e.switchname),
+        None => vdf::string_from_bytes(key).to_lowercase(),
     }
-    Some(map)
 }
 
-fn consume_u32(buffer: &[u8], index: &mut usize) -> Option<u32> {
-    Some(
-        ((consume_byte(buffer, index)? as u32) << 0)
-            + ((consume_byte(buffer, index)? as u32) << 8)
-            + ((consume_byte(buffer, index)? as u32) << 16)
-            + ((consume_byte(buffer, index)? as u32) << 24),
-    )
+pub fn consume_u32(buffer: &[u8], index: &mut usize) -> Option<u32> {
+    let bytes = buffer.get(*index..)?.get(..4)?;
+    *index += 4;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
 }
 
-fn consume_string(buffer: &[u8], index: &mut usize) -> Option<String> {
-    let mut word = String::new();
-    //println!("[{:x}] Consume string starts", *index);
-    loop {
-        //we need to handle utf-8 here
-
-        match consume_byte(buffer, index) {
-            Some(0) => break,
-            Some(c) => {
-                if c >= 128 {
-                    //utf-8
-                    let mut utf8_arr = vec![c];
-                    loop {
-                        if let Some(b) = peek_byte(buffer, *index) {
-                            if b < 128 {
-                                break;
-                            } else {
-                                utf8_arr.push(b);
-                                *index += 1;
-                            }
-                        } else {
-                            println!(
-                                "[{:x}] Invalid UTF8 chars. Word was: {:?}",
-                                *index - 1,
-                                &word
-                            );
-                            return None;
-                        }
-                    }
+/// Bytes up to the next NUL, which is consumed too.
+pub fn consume_slice<'a>(buffer: &'a [u8], index: &mut usize) -> Option<&'a [u8]> {
+    let rest = buffer.get(*index..)?;
+    let len = memchr::memchr(0, rest)?;
+    *index += len + 1;
+    Some(&rest[..len])
+}
 
-                    if utf8_arr.len() <= 1 {
-                        println!(
-                            "[{:x}] Invalid UTF8 chars. Word was: {:?}",
-                            *index - 1,
-                            &word
-                        );
-                        return None;
-                    }
-                    if let Ok(st) = from_utf8(&utf8_arr) {
-                        word.push_str(st);
-                    } else {
-                        println!(
-                            "[{:x}] Invalid UTF8 chars. Word was: {:?}",
-                            *index - 1,
-                            &word
-                        );
-                        return None;
-                    }
-                } else {
-                    //println!("[{:x}] Consume string push {:x} to {:?}", *index, c, word);
-                    word.push(c as char)
-                }
-            }
-            _ if word.len() > 0 => break,
-            _ => return None,
-        }
-    }
-    //println!("[{:x}] Consumed string {:?}", *index,word);
-    Some(word)
+/// Owned `consume_slice`.
+pub fn consume_bytes(buffer: &[u8], index: &mut usize) -> Option<Vec<u8>> {
+    consume_slice(buffer, index).map(<[u8]>::to_vec)
+}
+
+/// NUL terminated string. Invalid UTF-8 sequences are replaced, use
+/// `consume_bytes` to keep them.
+pub fn consume_string(buffer: &[u8], index: &mut usize) -> Option<String> {
+    consume_slice(buffer, index).map(|b| String::from_utf8_lossy(b).into_owned())
 }
 
-fn consume_byte(buffer: &[u8], index: &mut usize) -> Option<u8> {
+pub fn consume_byte(buffer: &[u8], index: &mut usize) -> Option<u8> {
     let c = peek_byte(buffer, *index);
     if c.is_some() {
         *index += 1;
         return c;
     }
-    return None;
+    None
 }
 
-fn peek_byte(buffer: &[u8], index: usize) -> Option<u8> {
+pub fn peek_byte(buffer: &[u8], index: usize) -> Option<u8> {
     if index >= buffer.len() {
         return None;
     }
@@ -744,11 +723,70 @@ impl Shortcut {
         }
     }
 
+    /// Read the items of an entry map, from right after its key up to and
+    /// including its end byte.
+    pub fn parse(buffer: &[u8], index: &mut usize, entry: u32) -> Result<Self, String> {
+        let mut props = HashMap::with_capacity(SHORTCUT_PROP_INFO.len());
+        while let Some((item_type, key)) = consume_item_header(buffer, index)? {
+            let value = match item_type {
+                VdfMapItemType::String => {
+                    let bytes =
+                        consume_slice(buffer, index).ok_or_else(|| eof(*index, "string value"))?;
+                    match std::str::from_utf8(bytes) {
+                        Ok(s) => ShortcutProp::String(s.to_owned()),
+                        Err(_) => ShortcutProp::Bytes(bytes.to_vec()),
+                    }
+                }
+                VdfMapItemType::UInt32 => ShortcutProp::UInt32(
+                    consume_u32(buffer, index).ok_or_else(|| eof(*index, "int32 value"))?,
+                ),
+                // Maps only, the end byte is never returned as an item.
+                _ => ShortcutProp::Strings(consume_strings(buffer, index)?),
+            };
+            props.insert(prop_name(key), value);
+        }
+        props.insert("index".to_owned(), ShortcutProp::UInt32(entry));
+        Ok(Self { props })
+    }
+
+    /// App id Steam computes for a non-Steam game: crc32 of exe followed by
+    /// app name, with the high bit set.
+    pub fn compute_app_id(&self) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        for key in ["exe", "app_name"] {
+            match self.props.get(key) {
+                Some(ShortcutProp::String(s)) => hasher.update(s.as_bytes()),
+                Some(ShortcutProp::Bytes(b)) => hasher.update(b),
+                _ => (),
+            }
+        }
+        hasher.finalize() | 0x80000000
+    }
+
+    /// Replace every `Bytes` prop, and every tag that is not UTF-8, by the
+    /// text `decode` makes of it.
+    pub fn decode_bytes(&mut self, decode: &dyn Fn(&[u8]) -> String) {
+        for prop in self.props.values_mut() {
+            match prop {
+                ShortcutProp::Bytes(b) => *prop = ShortcutProp::String(decode(b)),
+                ShortcutProp::Strings(arr) => {
+                    for s in arr.iter_mut() {
+                        if let Cow::Owned(b) = vdf::string_to_bytes(s) {
+                            *s = decode(&b);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
     pub fn prop_to_string(&self, key: &str) -> Option<String> {
         Some(match self.props.get(key) {
             Some(ShortcutProp::UInt32(n)) => format!("{}", n),
             Some(ShortcutProp::String(n)) => n.clone(),
-            Some(ShortcutProp::Strings(n)) => match serde_json::to_string(&n) {
+            Some(p @ ShortcutProp::Bytes(_)) => p.text().into_owned(),
+            Some(ShortcutProp::Strings(n)) => match serde_json::to_string(&vdf::lossy_all(n)) {
                 Ok(v) => v,
                 Err(_) => return None,
             },
@@ -756,6 +794,83 @@ impl Shortcut {
         })
     }
 
+    /// Set every known prop found in the json object `s`, keyed by switch name.
+    pub fn update_from_object(
+        &mut self,
+        s: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<(), String> {
+        for prop in SHORTCUT_PROP_INFO.iter() {
+            match &prop.type_default {
+                ShortcutProp::UInt32(_) => {
+                    if let Some(val) = s.get(prop.switchname) {
+                        *self.props.entry(String::from(prop.switchname)).or_default() =
+                            ShortcutProp::UInt32(from_json_number(val)?)
+                    }
+                }
+                ShortcutProp::String(_) => {
+                    if let Some(val) = s.get(prop.switchname) {
+                        *self.props.entry(String::from(prop.switchname)).or_default() =
+                            ShortcutProp::String(from_json_string(val)?);
+                    }
+                }
+                ShortcutProp::Strings(_) => {
+                    if let Some(val) = s.get(prop.switchname) {
+                        *self.props.entry(String::from(prop.switchname)).or_default() =
+                            ShortcutProp::Strings(from_json_array(val)?)
+                    }
+                }
+                ShortcutProp::None | ShortcutProp::Bytes(_) => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `update_from_object`, keeping the keys not described by
+    /// `SHORTCUT_PROP_INFO` as extra props. A text value replacing a number or
+    /// a list is converted back to it, as list exports write them as text.
+    pub fn update_from_record(
+        &mut self,
+        s: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<(), String> {
+        let s = record_object(s);
+        self.update_from_object(&s)?;
+        for (key, val) in &s {
+            if SHORTCUT_PROP_INFO.iter().any(|p| p.switchname == key) {
+                continue;
+            }
+            let prop = match (self.props.get(key), val) {
+                (_, serde_json::Value::Null) => continue,
+                (_, serde_json::Value::Number(_)) => ShortcutProp::UInt32(from_json_number(val)?),
+                (_, serde_json::Value::Array(_)) => ShortcutProp::Strings(from_json_array(val)?),
+                (Some(ShortcutProp::UInt32(_)), serde_json::Value::String(t)) => {
+                    ShortcutProp::UInt32(t.trim().parse().map_err(|_| {
+                        format!("Cannot convert \"{}\" of \"{}\" to u32", t, key)
+                    })?)
+                }
+                (Some(ShortcutProp::Strings(_)), serde_json::Value::String(t)) => {
+                    ShortcutProp::Strings(serde_json::from_str(t).map_err(|_| {
+                        format!(
+                            "Cannot convert \"{}\" of \"{}\" to a string array. Expected something like [\"str1\",\"str2\"].",
+                            t, key
+                        )
+                    })?)
+                }
+                (_, serde_json::Value::String(t)) => ShortcutProp::String(t.clone()),
+                _ => return Err(format!("Unsupported value {} for \"{}\"", val, key)),
+            };
+            self.props.insert(key.clone(), prop);
+        }
+        Ok(())
+    }
+
+    /// All props as a json object, keyed by switch name.
+    pub fn to_json(&self) -> serde_json::Map<String, serde_json::Value> {
+        self.props
+            .iter()
+            .filter_map(|(k, v)| Some((k.clone(), v.to_json()?)))
+            .collect()
+    }
+
     pub fn prop_to_u32(&self, key: &str) -> Option<u32> {
         match self.props.get(key) {
             Some(ShortcutProp::UInt32(n)) => Some(*n),
@@ -763,20 +878,18 @@ impl Shortcut {
         }
     }
 
-    fn write_into(&self, file: &mut File) -> Result<(), String> {
+    fn write_into(&self, file: &mut impl Write) -> Result<(), String> {
         write_type(file, VdfMapItemType::Map)?;
         write_string(
             file,
             self.prop_to_string("index")
-                .map_or(Err("Missing property Index".to_owned()), |f| Ok(f))?
+                .ok_or("Missing property Index".to_owned())?
                 .as_str(),
         )?;
         for i in 0..SHORTCUT_PROP_INFO.len() {
-            let prop_opt = SHORTCUT_PROP_INFO.iter().find(|e| e.order == i as u32);
-            if prop_opt.is_none() {
+            let Some(prop) = SHORTCUT_PROP_INFO.iter().find(|e| e.order == i as u32) else {
                 return Err(format!("Missing SHORTCUT_PROP_INFO with order {}", i));
-            }
-            let prop = prop_opt.unwrap();
+            };
             if prop.switchname == "index" {
                 continue;
             }
@@ -784,42 +897,229 @@ impl Shortcut {
                 ShortcutProp::UInt32(_) => write_type(file, VdfMapItemType::UInt32),
                 ShortcutProp::String(_) => write_type(file, VdfMapItemType::String),
                 ShortcutProp::Strings(_) => write_type(file, VdfMapItemType::Map),
-                ShortcutProp::None => unreachable!(),
+                ShortcutProp::None | ShortcutProp::Bytes(_) => unreachable!(),
             }?;
 
             write_string(file, prop.pascalcase)?;
 
             match &prop.type_default {
-                ShortcutProp::UInt32(def) => write_u32(
-                    file,
-                    &self.prop_to_u32(prop.switchname).or(Some(*def)).unwrap(),
-                ),
-                ShortcutProp::String(def) => write_string(
-                    file,
-                    self.prop_to_string(prop.switchname)
-                        .map_or(def.clone(), |s| s)
-                        .as_str(),
-                ),
-                ShortcutProp::Strings(_) => {
-                    if let ShortcutProp::Strings(arr) = &self.props[prop.switchname] {
-                        for (n, s) in arr.iter().enumerate() {
-                            write_type(file, VdfMapItemType::String)?;
-                            write_string(file, format!("{}", n).as_str())?;
-                            write_string(file, escape_json_string(s).as_str())?;
-                        }
-                        write_type(file, VdfMapItemType::MapEnd)?;
-                    };
-                    Ok(())
+                ShortcutProp::UInt32(def) => {
+                    write_u32(file, &self.prop_to_u32(prop.switchname).unwrap_or(*def))
                 }
-                ShortcutProp::None => unreachable!(),
+                ShortcutProp::String(def) => match self.props.get(prop.switchname) {
+                    Some(ShortcutProp::String(s)) => write_string(file, s),
+                    Some(ShortcutProp::Bytes(b)) => write_bytes(file, b),
+                    _ => write_string(
+                        file,
+                        self.prop_to_string(prop.switchname)
+                            .map_or(def.clone(), |s| s)
+                            .as_str(),
+                    ),
+                },
+                ShortcutProp::Strings(_) => match self.props.get(prop.switchname) {
+                    Some(ShortcutProp::Strings(arr)) => write_strings(file, arr),
+                    _ => write_strings(file, &[]),
+                },
+                ShortcutProp::None | ShortcutProp::Bytes(_) => unreachable!(),
             }?;
         }
 
+        for (key, prop) in self.extra_props() {
+            match prop {
+                ShortcutProp::UInt32(n) => {
+                    write_type(file, VdfMapItemType::UInt32)?;
+                    write_raw_string(file, key)?;
+                    write_u32(file, n)?;
+                }
+                ShortcutProp::String(s) => {
+                    write_type(file, VdfMapItemType::String)?;
+                    write_raw_string(file, key)?;
+                    write_string(file, s)?;
+                }
+                ShortcutProp::Bytes(b) => {
+                    write_type(file, VdfMapItemType::String)?;
+                    write_raw_string(file, key)?;
+                    write_bytes(file, b)?;
+                }
+                ShortcutProp::Strings(arr) => {
+                    write_type(file, VdfMapItemType::Map)?;
+                    write_raw_string(file, key)?;
+                    write_strings(file, arr)?;
+                }
+                ShortcutProp::None => (),
+            }
+        }
+
         write_type(file, VdfMapItemType::MapEnd)?;
         Ok(())
     }
+
+    /// Props not described by `SHORTCUT_PROP_INFO`, sorted by key.
+    pub fn extra_props(&self) -> Vec<(&String, &ShortcutProp)> {
+        let mut extra: Vec<(&String, &ShortcutProp)> = self
+            .props
+            .iter()
+            .filter(|(k, _)| !SHORTCUT_PROP_INFO.iter().any(|p| p.switchname == *k))
+            .collect();
+        extra.sort_by_key(|(a, _)| *a);
+        extra
+    }
+}
+
+fn write_strings(file: &mut impl Write, arr: &[String]) -> Result<(), String> {
+    for (n, s) in arr.iter().enumerate() {
+        write_type(file, VdfMapItemType::String)?;
+        write_string(file, format!("{}", n).as_str())?;
+        write_raw_string(file, s)?;
+    }
+    write_type(file, VdfMapItemType::MapEnd)
+}
+
+/// Builders for the entries used by the unit tests of every module.
+#[cfg(test)]
+impl ShortcutProp {
+    pub fn string(s: &str) -> Self {
+        ShortcutProp::String(s.to_owned())
+    }
+}
+
+#[cfg(test)]
+impl Shortcut {
+    pub fn with(mut self, key: &str, value: ShortcutProp) -> Self {
+        self.props.insert(key.to_owned(), value);
+        self
+    }
+
+    pub fn with_string(self, key: &str, value: &str) -> Self {
+        self.with(key, ShortcutProp::string(value))
+    }
+
+    pub fn with_u32(self, key: &str, value: u32) -> Self {
+        self.with(key, ShortcutProp::UInt32(value))
+    }
+
+    pub fn with_tags(self, tags: &[&str]) -> Self {
+        self.with(
+            "tags",
+            ShortcutProp::Strings(tags.iter().map(|t| t.to_string()).collect()),
+        )
+    }
+}
+
+#[cfg(test)]
+impl Shortcuts {
+    /// `entries` at indices 0, 1, ...
+    pub fn from_entries(entries: impl IntoIterator<Item = Shortcut>) -> Self {
+        let mut scs = Shortcuts::empty();
+        for sc in entries {
+            scs.push(sc);
+        }
+        scs
+    }
 }
 
-fn escape_json_string(s: &str) -> String {
-    s.replace("\\", "\\\\").replace("\"", "\\\"")
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn names(scs: &Shortcuts) -> Vec<(u32, String)> {
+        scs.iter()
+            .map(|sc| {
+                (
+                    sc.prop_to_u32("index").unwrap(),
+                    sc.prop_to_string("app_name").unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn json_entries_go_to_their_index() {
+        let mut scs = Shortcuts::empty();
+        let values = [
+            json!({"index": 1, "app_name": "B", "exe": "b"}),
+            json!({"index": 0, "app_name": "A", "exe": "a"}),
+        ];
+        scs.update_from_values(&values).unwrap();
+        assert_eq!(names(&scs), [(0, "A".to_owned()), (1, "B".to_owned())]);
+    }
+
+    #[test]
+    fn records_go_to_their_index_and_the_others_are_appended() {
+        let mut scs = Shortcuts::empty();
+        scs.update_from_values(&[json!({"index": 0, "app_name": "A"})])
+            .unwrap();
+        let records = [
+            json!({"index": 5, "app_name": "Far"}),
+            json!({"index": 0, "app_name": "A2"}),
+            json!({"app_name": "No index"}),
+            json!({"index": 1, "app_name": "One"}),
+        ];
+        scs.import_records(&records).unwrap();
+        assert_eq!(
+            names(&scs),
+            [
+                (0, "A2".to_owned()),
+                (1, "One".to_owned()),
+                (5, "Far".to_owned()),
+                (6, "No index".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn records_keep_unknown_keys_with_their_type() {
+        let mut scs = Shortcuts::from_entries([Shortcut::empty()
+            .with_u32("isinstalled", 1)
+            .with("collections", ShortcutProp::Strings(vec!["x".to_owned()]))]);
+        // As read back from a CSV export: every cell is text.
+        let records = [json!({
+            "index": 0,
+            "isinstalled": "0",
+            "collections": "[\"a\",\"b\"]",
+            "sortas": "Zed",
+        })];
+        scs.import_records(&records).unwrap();
+        let sc = scs.at(&0).unwrap();
+        assert_eq!(sc.prop_to_u32("isinstalled"), Some(0));
+        assert_eq!(
+            sc.prop_to_string("collections").as_deref(),
+            Some("[\"a\",\"b\"]")
+        );
+        assert_eq!(sc.prop_to_string("sortas").as_deref(), Some("Zed"));
+
+        let bad = [json!({"index": 0, "isinstalled": "yes"})];
+        assert!(scs.import_records(&bad).is_err());
+    }
+
+    #[test]
+    fn records_accept_every_name_of_a_field_and_booleans() {
+        let mut scs = Shortcuts::empty();
+        let records = [
+            json!({"Index": 2, "AppName": "A", "exe": "/bin/a", "IsHidden": true}),
+            json!({"appname": "B", "allowoverlay": false, "SortAs": "Bee"}),
+            json!({"app_name": "C", "last_play_time_iso": "1970-01-01", "open_vr": 1}),
+        ];
+        scs.import_records(&records).unwrap();
+        assert_eq!(
+            names(&scs),
+            [
+                (2, "A".to_owned()),
+                (3, "B".to_owned()),
+                (4, "C".to_owned())
+            ]
+        );
+        let a = scs.at(&2).unwrap();
+        assert_eq!(a.prop_to_u32("is_hidden"), Some(1));
+        assert_eq!(a.extra_props(), []);
+        let b = scs.at(&3).unwrap();
+        assert_eq!(b.prop_to_u32("allow_overlay"), Some(0));
+        assert_eq!(b.prop_to_string("sortas").as_deref(), Some("Bee"));
+        let c = scs.at(&4).unwrap();
+        assert_eq!(c.prop_to_u32("open_vr"), Some(1));
+        assert!(!c.props.contains_key("last_play_time_iso"));
+
+        assert!(scs.import_records(&[json!({"app_name": true})]).is_err());
+    }
 }
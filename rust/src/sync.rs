@@ -0,0 +1,345 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use std::{collections::HashSet, fmt::Display};
+
+use crate::shortcuts::{Shortcut, ShortcutProp, Shortcuts};
+
+/// How `sync` recognizes the entries it owns. Anything else was added by hand
+/// and is never modified or removed.
+pub enum Tracking {
+    /// Managed entries carry this tag.
+    Marker(String),
+    /// Managed entries are the ones whose app_id was stored by a previous run.
+    State(HashSet<u32>),
+}
+
+impl Tracking {
+    fn is_managed(&self, sc: &Shortcut) -> bool {
+        match self {
+            Tracking::Marker(tag) => {
+                matches!(sc.props.get("tags"), Some(ShortcutProp::Strings(tags)) if tags.contains(tag))
+            }
+            Tracking::State(ids) => sc.prop_to_u32("app_id").is_some_and(|id| ids.contains(&id)),
+        }
+    }
+}
+
+pub enum SyncAction {
+    Add {
+        index: u32,
+        name: String,
+        app_id: u32,
+    },
+    Update {
+        index: u32,
+        name: String,
+        fields: Vec<String>,
+    },
+    Remove {
+        index: u32,
+        name: String,
+    },
+    Skip {
+        name: String,
+        reason: String,
+    },
+}
+
+impl SyncAction {
+    pub fn is_change(&self) -> bool {
+        !matches!(self, SyncAction::Skip { .. })
+    }
+}
+
+impl Display for SyncAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncAction::Add {
+                index,
+                name,
+                app_id,
+            } => {
+                write!(f, "+ [{}] {:?} added (app_id {})", index, name, app_id)
+            }
+            SyncAction::Update {
+                index,
+                name,
+                fields,
+            } => {
+                write!(f, "~ [{}] {:?} updated: {}", index, name, fields.join(", "))
+            }
+            SyncAction::Remove { index, name } => write!(f, "- [{}] {:?} removed", index, name),
+            SyncAction::Skip { name, reason } => write!(f, "! {:?} skipped: {}", name, reason),
+        }
+    }
+}
+
+pub struct SyncReport {
+    pub actions: Vec<SyncAction>,
+    /// App ids of every entry owned by the manifest after the sync.
+    pub managed_ids: Vec<u32>,
+}
+
+/// Bring `scs` in line with the `manifest` entries.
+///
+/// Manifest entries need at least `app_name` and `exe`; `app_id` is computed
+/// like Steam does when missing. They are matched with managed entries by
+/// app_id first, then by app_name, so renaming an exe keeps the same entry
+/// and, unless the manifest sets one, its app_id (Steam keys its artwork on
+/// it). Indices are left as they are: entries are added at the end and
+/// removed ones leave a gap.
+pub fn sync(
+    scs: &mut Shortcuts,
+    manifest: &[serde_json::Value],
+    tracking: &Tracking,
+) -> Result<SyncReport, String> {
+    let managed: Vec<u32> = scs
+        .iter()
+        .filter(|sc| tracking.is_managed(sc))
+        .filter_map(|sc| sc.prop_to_u32("index"))
+        .collect();
+    let mut unmatched = managed.clone();
+    let mut actions = vec![];
+    let mut managed_ids = vec![];
+
+    for (i, value) in manifest.iter().enumerate() {
+        let desired = desired_shortcut(value, tracking)
+            .map_err(|e| format!("Manifest entry {}: {}", i, e))?;
+        let name = desired.prop_to_string("app_name").unwrap_or_default();
+        let app_id = desired
+            .prop_to_u32("app_id")
+            .unwrap_or_else(|| desired.compute_app_id());
+
+        if managed_ids.contains(&app_id) {
+            actions.push(SyncAction::Skip {
+                name,
+                reason: format!(
+                    "app_id {} is already used by another manifest entry",
+                    app_id
+                ),
+            });
+            continue;
+        }
+
+        let found = unmatched
+            .iter()
+            .position(|idx| scs.at(idx).and_then(|sc| sc.prop_to_u32("app_id")) == Some(app_id))
+            .or_else(|| {
+                unmatched.iter().position(|idx| {
+                    scs.at(idx).and_then(|sc| sc.prop_to_string("app_name")) == Some(name.clone())
+                })
+            });
+
+        match found {
+            Some(pos) => {
+                let index = unmatched.remove(pos);
+                if let Some(sc) = scs.at_mut(&index) {
+                    let fields = apply(sc, &desired);
+                    if !fields.is_empty() {
+                        actions.push(SyncAction::Update {
+                            index,
+                            name,
+                            fields,
+                        });
+                    }
+                    managed_ids.push(sc.prop_to_u32("app_id").unwrap_or(app_id));
+                }
+            }
+            None => {
+                let taken = scs.iter().any(|sc| {
+                    !managed.contains(&sc.prop_to_u32("index").unwrap_or(u32::MAX))
+                        && sc.prop_to_u32("app_id") == Some(app_id)
+                });
+                if taken {
+                    actions.push(SyncAction::Skip {
+                        name,
+                        reason: format!(
+                            "an entry not managed by sync already uses app_id {}",
+                            app_id
+                        ),
+                    });
+                } else {
+                    let mut desired = desired;
+                    desired
+                        .props
+                        .insert("app_id".to_owned(), ShortcutProp::UInt32(app_id));
+                    let index = scs.push(desired);
+                    actions.push(SyncAction::Add {
+                        index,
+                        name,
+                        app_id,
+                    });
+                    managed_ids.push(app_id);
+                }
+            }
+        }
+    }
+
+    for index in unmatched {
+        if let Some(sc) = scs.remove(&index) {
+            actions.push(SyncAction::Remove {
+                index,
+                name: sc.prop_to_string("app_name").unwrap_or_default(),
+            });
+        }
+    }
+
+    managed_ids.sort();
+    Ok(SyncReport {
+        actions,
+        managed_ids,
+    })
+}
+
+fn desired_shortcut(value: &serde_json::Value, tracking: &Tracking) -> Result<Shortcut, String> {
+    let mut object = value
+        .as_object()
+        .ok_or("entries must be objects".to_owned())?
+        .clone();
+    object.remove("index");
+
+    let mut sc = Shortcut::empty();
+    sc.update_from_object(&object)?;
+    for required in ["app_name", "exe"] {
+        if sc.prop_to_string(required).is_none() {
+            return Err(format!("missing \"{}\"", required));
+        }
+    }
+    if let Tracking::Marker(tag) = tracking {
+        let mut tags: Vec<String> = match sc.props.get("tags") {
+            Some(ShortcutProp::Strings(tags)) => tags.clone(),
+            _ => vec![],
+        };
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+        sc.props
+            .insert("tags".to_owned(), ShortcutProp::Strings(tags));
+    }
+    Ok(sc)
+}
+
+/// Copy the props of `desired` into `sc`, returning the names of the changed ones.
+fn apply(sc: &mut Shortcut, desired: &Shortcut) -> Vec<String> {
+    let mut fields: Vec<String> = desired
+        .props
+        .iter()
+        .filter(|(k, v)| sc.props.get(*k) != Some(v))
+        .map(|(k, _)| k.clone())
+        .collect();
+    fields.sort();
+    for k in &fields {
+        sc.props.insert(k.clone(), desired.props[k].clone());
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sparse() -> Shortcuts {
+        Shortcuts::parse(include_bytes!("../tests/corpus/sparse_indices.vdf"), &mut 0).unwrap()
+    }
+
+    fn bytes(scs: &Shortcuts) -> Vec<u8> {
+        let mut out = vec![];
+        scs.store_into(&mut out).unwrap();
+        out
+    }
+
+    fn marker() -> Tracking {
+        Tracking::Marker("managed".to_owned())
+    }
+
+    #[test]
+    fn entries_are_added_at_the_end_and_hand_entries_are_untouched() {
+        let before = sparse();
+        let mut scs = before.clone();
+        let manifest = [json!({"app_name": "Synced", "exe": "/bin/synced"})];
+        let report = sync(&mut scs, &manifest, &marker()).unwrap();
+
+        let app_id = scs.at(&8).unwrap().prop_to_u32("app_id").unwrap();
+        assert_eq!(app_id, scs.at(&8).unwrap().compute_app_id());
+        let printed: Vec<String> = report.actions.iter().map(|a| a.to_string()).collect();
+        assert_eq!(
+            printed,
+            [format!("+ [8] \"Synced\" added (app_id {})", app_id)]
+        );
+        for index in [0, 3, 7] {
+            assert_eq!(
+                scs.at(&index).unwrap().props,
+                before.at(&index).unwrap().props
+            );
+        }
+
+        // Removing it leaves the others where they were.
+        let report = sync(&mut scs, &[], &marker()).unwrap();
+        assert_eq!(report.actions[0].to_string(), "- [8] \"Synced\" removed");
+        assert_eq!(bytes(&scs), bytes(&before));
+    }
+
+    #[test]
+    fn a_second_run_has_no_changes() {
+        let mut scs = sparse();
+        let manifest = [
+            json!({"app_name": "A", "exe": "/bin/a", "tags": ["x"]}),
+            json!({"app_name": "B", "exe": "/bin/b", "app_id": 12345}),
+        ];
+        sync(&mut scs, &manifest, &marker()).unwrap();
+        let first = bytes(&scs);
+
+        let report = sync(&mut scs, &manifest, &marker()).unwrap();
+        assert!(!report.actions.iter().any(|a| a.is_change()));
+        assert_eq!(bytes(&scs), first);
+    }
+
+    #[test]
+    fn matched_entries_keep_their_app_id() {
+        let mut scs = sparse();
+        let tracking = Tracking::State(HashSet::new());
+        let manifest = [json!({"app_name": "A", "exe": "/bin/a"})];
+        let first = sync(&mut scs, &manifest, &tracking).unwrap();
+        let app_id = first.managed_ids[0];
+
+        let tracking = Tracking::State(first.managed_ids.into_iter().collect());
+        let manifest = [json!({"app_name": "A", "exe": "/bin/new"})];
+        let report = sync(&mut scs, &manifest, &tracking).unwrap();
+        assert_eq!(report.actions[0].to_string(), "~ [8] \"A\" updated: exe");
+        assert_eq!(report.managed_ids, [app_id]);
+        assert_eq!(scs.at(&8).unwrap().prop_to_u32("app_id"), Some(app_id));
+
+        // An app_id set by the manifest still wins.
+        let manifest = [json!({"app_name": "A", "exe": "/bin/new", "app_id": 7})];
+        let report = sync(&mut scs, &manifest, &tracking).unwrap();
+        assert_eq!(report.actions[0].to_string(), "~ [8] \"A\" updated: app_id");
+        assert_eq!(report.managed_ids, [7]);
+    }
+
+    #[test]
+    fn entries_not_managed_are_never_taken_over() {
+        let mut scs = sparse();
+        let first = scs.at(&0).unwrap();
+        let manifest = [json!({
+            "app_name": "Mine",
+            "exe": "/bin/mine",
+            "app_id": first.prop_to_u32("app_id").unwrap(),
+        })];
+        let before = bytes(&scs);
+        let report = sync(&mut scs, &manifest, &marker()).unwrap();
+        assert!(report.actions[0]
+            .to_string()
+            .starts_with("! \"Mine\" skipped: an entry not managed by sync"));
+        assert_eq!(bytes(&scs), before);
+        assert!(sync(&mut scs, &[json!({"app_name": "No exe"})], &marker()).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const COLUMN_GAP: &str = "  ";
+const ELLIPSIS: char = '…';
+/// Columns are never truncated below this width (or their header width).
+const MIN_TRUNCATED_WIDTH: usize = 12;
+
+pub struct TableColumn {
+    pub header: String,
+    pub align_right: bool,
+    /// Whether the renderer may shorten values to fit the terminal.
+    pub truncatable: bool,
+}
+
+/// Render an aligned table with a header row. When `max_width` is set and the
+/// table is wider, the widest truncatable columns are shortened in the middle
+/// so both the start and the end of long paths stay visible.
+pub fn render(columns: &[TableColumn], rows: &[Vec<String>], max_width: Option<usize>) -> String {
+    let mut widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            rows.iter()
+                .map(|r| r[i].width())
+                .chain(std::iter::once(c.header.width()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    if let Some(max_width) = max_width {
+        shrink(columns, &mut widths, max_width);
+    }
+
+    let mut out = vec![
+        format_row(
+            columns,
+            &widths,
+            &columns
+                .iter()
+                .map(|c| c.header.clone())
+                .collect::<Vec<String>>(),
+        ),
+        widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<String>>()
+            .join(COLUMN_GAP),
+    ];
+    out.extend(rows.iter().map(|r| format_row(columns, &widths, r)));
+    out.join("\n")
+}
+
+fn shrink(columns: &[TableColumn], widths: &mut [usize], max_width: usize) {
+    let gaps = COLUMN_GAP.width() * widths.len().saturating_sub(1);
+    loop {
+        let total: usize = widths.iter().sum::<usize>() + gaps;
+        if total <= max_width {
+            return;
+        }
+        let mut candidates: Vec<usize> = (0..widths.len())
+            .filter(|i| columns[*i].truncatable && widths[*i] > floor(&columns[*i]))
+            .collect();
+        candidates.sort_by_key(|i| std::cmp::Reverse(widths[*i]));
+        match candidates.first() {
+            // Bring the widest column down towards the next one so the space is
+            // taken evenly from every long column.
+            Some(&i) => {
+                let next = candidates.get(1).map_or(0, |j| widths[*j]);
+                let target = (widths[i].saturating_sub(total - max_width))
+                    .max(next)
+                    .max(floor(&columns[i]));
+                widths[i] = if target < widths[i] {
+                    target
+                } else {
+                    widths[i] - 1
+                };
+            }
+            None => return,
+        }
+    }
+}
+
+fn floor(column: &TableColumn) -> usize {
+    MIN_TRUNCATED_WIDTH.max(column.header.width())
+}
+
+fn format_row(columns: &[TableColumn], widths: &[usize], cells: &[String]) -> String {
+    let line = cells
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| {
+            let cell = truncate_middle(cell, widths[i]);
+            let pad = " ".repeat(widths[i] - cell.width());
+            if columns[i].align_right {
+                format!("{}{}", pad, cell)
+            } else {
+                format!("{}{}", cell, pad)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(COLUMN_GAP);
+    line.trim_end().to_owned()
+}
+
+/// Shorten `s` to at most `width` display columns by replacing its middle with
+/// an ellipsis. Double width characters are never split.
+pub fn truncate_middle(s: &str, width: usize) -> String {
+    if s.width() <= width {
+        return s.to_owned();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let budget = width - 1;
+    let head_budget = budget - budget / 2;
+    let tail_budget = budget / 2;
+
+    let mut head = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > head_budget {
+            break;
+        }
+        used += w;
+        head.push(c);
+    }
+
+    let mut tail: Vec<char> = vec![];
+    let mut used = 0;
+    for c in s.chars().rev() {
+        let w = c.width().unwrap_or(0);
+        if used + w > tail_budget {
+            break;
+        }
+        used += w;
+        tail.push(c);
+    }
+
+    format!(
+        "{}{}{}",
+        head,
+        ELLIPSIS,
+        tail.into_iter().rev().collect::<String>()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(header: &str, align_right: bool, truncatable: bool) -> TableColumn {
+        TableColumn {
+            header: header.to_owned(),
+            align_right,
+            truncatable,
+        }
+    }
+
+    fn row(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn middle_of_long_values_is_cut() {
+        assert_eq!(truncate_middle("/home/deck/game.exe", 9), "/hom….exe");
+        assert_eq!(truncate_middle("short", 9), "short");
+        assert_eq!(truncate_middle("abc", 0), "");
+        // Double width characters are never split: the result may be
+        // narrower than asked.
+        let cut = truncate_middle("東方紅魔郷東方紅魔郷", 8);
+        assert_eq!(cut, "東方…郷");
+        assert!(cut.width() <= 8);
+    }
+
+    #[test]
+    fn columns_are_aligned_and_lines_trimmed() {
+        let columns = [column("idx", true, false), column("name", false, false)];
+        let rows = [row(&["0", "Game"]), row(&["12", "Émulateur"])];
+        assert_eq!(
+            render(&columns, &rows, None),
+            "idx  name\n\
+             ---  ---------\n\
+             \x20 0  Game\n\
+             \x2012  Émulateur"
+        );
+    }
+
+    #[test]
+    fn only_truncatable_columns_shrink_and_never_below_their_floor() {
+        let columns = [column("name", false, false), column("exe", false, true)];
+        let long = format!("/{}", "x".repeat(40));
+        let rows = [row(&["A long game name", &long])];
+
+        let table = render(&columns, &rows, Some(40));
+        let last = table.lines().last().unwrap();
+        assert!(last.starts_with("A long game name  "));
+        assert_eq!(last.width(), 40);
+        assert!(last.contains(ELLIPSIS));
+
+        let table = render(&columns, &rows, Some(10));
+        let last = table.lines().last().unwrap();
+        assert_eq!(last.width(), 16 + COLUMN_GAP.len() + MIN_TRUNCATED_WIDTH);
+    }
+}
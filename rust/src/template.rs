@@ -0,0 +1,338 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, NaiveDateTime, TimeZone};
+
+use crate::columns::{self, Column};
+use crate::shortcuts::{Shortcut, ShortcutProp, Shortcuts};
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Output template for `list --format`, e.g.
+/// `{app_id}\t{app_name|upper}\t{last_play_time|date:%F}`.
+///
+/// Fields use the same names as `--columns`. Literal braces are written as
+/// `{{` and `}}`, and `\t`, `\n`, `\\` are unescaped in the literal text.
+#[derive(Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug)]
+enum Part {
+    Literal(String),
+    Field {
+        column: Column,
+        filters: Vec<Filter>,
+    },
+}
+
+#[derive(Debug)]
+enum Filter {
+    Upper,
+    Lower,
+    Trim,
+    /// Double quoted with `"` and `\` escaped.
+    Quote,
+    /// Single quoted for POSIX shells, when needed.
+    Shell,
+    /// JSON value: numbers stay numbers, tags become an array.
+    Json,
+    /// Format a unix timestamp in local time.
+    Date(String),
+    /// Format a unix timestamp in UTC.
+    UtcDate(String),
+    /// Replace an empty value.
+    Default(String),
+}
+
+impl Filter {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let (name, arg) = match spec.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg)),
+            None => (spec.trim(), None),
+        };
+        Ok(match (name, arg) {
+            ("upper", None) => Filter::Upper,
+            ("lower", None) => Filter::Lower,
+            ("trim", None) => Filter::Trim,
+            ("quote", None) => Filter::Quote,
+            ("shell", None) => Filter::Shell,
+            ("json", None) => Filter::Json,
+            ("date", f) => Filter::Date(date_format(f)?),
+            ("utcdate", f) => Filter::UtcDate(date_format(f)?),
+            ("default", Some(d)) => Filter::Default(d.to_owned()),
+            _ => return Err(format!("Unknown template filter \"{}\".", spec)),
+        })
+    }
+}
+
+/// Check a `date` or `utcdate` format once, as chrono panics while
+/// formatting with an invalid one.
+fn date_format(spec: Option<&str>) -> Result<String, String> {
+    let spec = spec.unwrap_or(DEFAULT_DATE_FORMAT);
+    if StrftimeItems::new(spec).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("Invalid date format \"{}\".", spec));
+    }
+    Ok(spec.to_owned())
+}
+
+/// Value flowing through the filters. `prop` is kept until a filter turns the
+/// value into plain text, so `json` and `date` can still see the original type.
+struct FieldValue<'a> {
+    text: String,
+    prop: Option<&'a ShortcutProp>,
+}
+
+impl Template {
+    pub fn parse(spec: &str, scs: &Shortcuts) -> Result<Self, String> {
+        let extra = columns::extra_keys(scs);
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut chars = spec.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => return Err(format!("Unterminated field \"{{{}\".", field)),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    let mut pieces = field.split('|');
+                    let column = columns::resolve_one(pieces.next().unwrap_or(""), &extra)?;
+                    let filters = pieces
+                        .map(Filter::parse)
+                        .collect::<Result<Vec<Filter>, String>>()?;
+                    parts.push(Part::Field { column, filters });
+                }
+                '}' => return Err("Unmatched \"}\". Use \"}}\" for a literal brace.".to_owned()),
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    Some(o) => {
+                        literal.push('\\');
+                        literal.push(o);
+                    }
+                    None => literal.push('\\'),
+                },
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    pub fn render(&self, sc: &Shortcut) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(s) => s.clone(),
+                Part::Field { column, filters } => {
+                    let value = FieldValue {
+                        text: column.text(sc),
+                        prop: match column {
+                            Column::Prop(_) | Column::Extra(_) => Some(column.prop(sc)),
+                            _ => None,
+                        },
+                    };
+                    filters
+                        .iter()
+                        .fold(value, |value, filter| apply(filter, value))
+                        .text
+                }
+            })
+            .collect()
+    }
+}
+
+fn apply<'a>(filter: &Filter, value: FieldValue<'a>) -> FieldValue<'a> {
+    let text = match filter {
+        Filter::Upper => value.text.to_uppercase(),
+        Filter::Lower => value.text.to_lowercase(),
+        Filter::Trim => value.text.trim().to_owned(),
+        Filter::Quote => format!(
+            "\"{}\"",
+            value.text.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+        Filter::Shell => shell_escape(&value.text),
+        Filter::Json => match value.prop {
+            Some(ShortcutProp::UInt32(n)) => n.to_string(),
+            Some(ShortcutProp::Strings(arr)) => serde_json::to_string(arr).unwrap_or_default(),
+            _ => serde_json::to_string(&value.text).unwrap_or_default(),
+        },
+        Filter::Date(fmt) | Filter::UtcDate(fmt) => {
+            let secs = match value.prop {
+                Some(ShortcutProp::UInt32(n)) => Some(*n as i64),
+                _ => value.text.trim().parse::<i64>().ok(),
+            };
+            match secs {
+                Some(secs) if matches!(filter, Filter::Date(_)) => Local
+                    .timestamp_opt(secs, 0)
+                    .single()
+                    .map_or(value.text, |d| d.format(fmt).to_string()),
+                Some(secs) => NaiveDateTime::from_timestamp_opt(secs, 0)
+                    .map_or(value.text, |d| d.format(fmt).to_string()),
+                None => value.text,
+            }
+        }
+        Filter::Default(d) => {
+            if value.text.is_empty() {
+                d.clone()
+            } else {
+                value.text
+            }
+        }
+    };
+    FieldValue { text, prop: None }
+}
+
+/// Quote `s` for POSIX shells unless it only contains safe characters.
+pub fn shell_escape(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c))
+    {
+        return s.to_owned();
+    }
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> Shortcut {
+        Shortcut::empty()
+            .with_u32("app_id", 42)
+            .with_string("app_name", " My \"Game\" ")
+            .with_string("exe", "/opt/it's here/run")
+            .with_string("icon", "")
+            .with_u32("last_play_time", 1_600_000_000)
+            .with_tags(&["a", "b"])
+            .with_string("sortas", "Zed")
+    }
+
+    fn render(spec: &str) -> Result<String, String> {
+        let sc = game();
+        let scs = Shortcuts::from_entries([game()]);
+        Template::parse(spec, &scs).map(|t| t.render(&sc))
+    }
+
+    #[test]
+    fn fields_and_literals_are_rendered_in_order() {
+        assert_eq!(
+            render("{app_id}\\t{AppId} {appid}\\n").unwrap(),
+            "42\t42 42\n"
+        );
+        assert_eq!(render("[{sortas}] {tags}").unwrap(), "[Zed] a, b");
+        assert_eq!(render("a\\b\\\\").unwrap(), "a\\b\\");
+        assert_eq!(render("").unwrap(), "");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{{app_id}}}").unwrap(), "{42}");
+        assert_eq!(render("}}{{").unwrap(), "}{");
+        assert_eq!(
+            render("{app_id").unwrap_err(),
+            "Unterminated field \"{app_id\"."
+        );
+        assert_eq!(
+            render("app_id}").unwrap_err(),
+            "Unmatched \"}\". Use \"}}\" for a literal brace."
+        );
+    }
+
+    #[test]
+    fn text_filters() {
+        assert_eq!(render("{app_name|upper}").unwrap(), " MY \"GAME\" ");
+        assert_eq!(render("{app_name|lower}").unwrap(), " my \"game\" ");
+        assert_eq!(render("{app_name|trim}").unwrap(), "My \"Game\"");
+        assert_eq!(
+            render("{app_name|trim|quote}").unwrap(),
+            "\"My \\\"Game\\\"\""
+        );
+        assert_eq!(render("{exe|shell}").unwrap(), "'/opt/it'\\''s here/run'");
+        assert_eq!(render("{sortas|shell}").unwrap(), "Zed");
+        assert_eq!(render("{icon|default:none}").unwrap(), "none");
+        assert_eq!(render("{sortas|default:none}").unwrap(), "Zed");
+    }
+
+    #[test]
+    fn json_filter_keeps_the_type() {
+        assert_eq!(render("{app_id|json}").unwrap(), "42");
+        assert_eq!(render("{tags|json}").unwrap(), "[\"a\",\"b\"]");
+        assert_eq!(render("{app_name|json}").unwrap(), "\" My \\\"Game\\\" \"");
+        // After a text filter the value is a string.
+        assert_eq!(render("{app_id|trim|json}").unwrap(), "\"42\"");
+    }
+
+    #[test]
+    fn date_filters() {
+        assert_eq!(
+            render("{last_play_time|utcdate}").unwrap(),
+            "2020-09-13 12:26:40"
+        );
+        assert_eq!(
+            render("{last_play_time|utcdate:%F %H}").unwrap(),
+            "2020-09-13 12"
+        );
+        assert_eq!(render("{last_play_time|date:%Y}").unwrap(), "2020");
+        // Text that is not a timestamp is left alone.
+        assert_eq!(render("{sortas|utcdate}").unwrap(), "Zed");
+    }
+
+    #[test]
+    fn bad_date_formats_are_errors() {
+        for spec in ["{last_play_time|date:%Q}", "{last_play_time|utcdate:%}"] {
+            assert!(
+                render(spec).unwrap_err().starts_with("Invalid date format"),
+                "{}",
+                spec
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_fields_and_filters_are_errors() {
+        assert_eq!(
+            render("{nothing}").unwrap_err(),
+            "Unknown column \"nothing\"."
+        );
+        assert_eq!(
+            render("{app_id|bold}").unwrap_err(),
+            "Unknown template filter \"bold\"."
+        );
+        assert_eq!(
+            render("{app_id|upper:x}").unwrap_err(),
+            "Unknown template filter \"upper:x\"."
+        );
+        assert!(render("{app_id|default}").is_err());
+    }
+}
@@ -0,0 +1,745 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Generic KeyValues tree, independent of the shortcuts schema. Keys keep
+//! their order and case and values keep their binary type, so a file can be
+//! converted to JSON or text and back to the very same bytes.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+/// Binary type bytes.
+pub const TYPE_MAP: u8 = 0x00;
+pub const TYPE_STRING: u8 = 0x01;
+pub const TYPE_INT32: u8 = 0x02;
+pub const TYPE_FLOAT: u8 = 0x03;
+pub const TYPE_POINTER: u8 = 0x04;
+pub const TYPE_WSTRING: u8 = 0x05;
+pub const TYPE_COLOR: u8 = 0x06;
+pub const TYPE_UINT64: u8 = 0x07;
+pub const TYPE_MAP_END: u8 = 0x08;
+pub const TYPE_INT64: u8 = 0x0A;
+
+/// Deepest map nesting the readers accept, so that a crafted file cannot
+/// exhaust the stack.
+pub const MAX_DEPTH: usize = 64;
+
+/// Binary strings are bytes, most often UTF-8 but not always: old files may
+/// hold CP-1252 names. Bytes that are not UTF-8 are kept in a `String` as the
+/// private use character U+10FF00 + byte. Real characters of that range are
+/// written the same way, byte by byte, so every byte string reads back
+/// unchanged.
+const RAW_BYTES: u32 = 0x10FF00;
+
+fn raw_char(b: u8) -> char {
+    char::from_u32(RAW_BYTES + b as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+fn is_raw(c: char) -> bool {
+    c as u32 >= RAW_BYTES
+}
+
+/// Text of a binary string, whatever its bytes. See `string_to_bytes`.
+pub fn string_from_bytes(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if is_raw(c) {
+                out.extend(c.encode_utf8(&mut [0; 4]).bytes().map(raw_char));
+            } else {
+                out.push(c);
+            }
+        }
+        out.extend(chunk.invalid().iter().copied().map(raw_char));
+    }
+    out
+}
+
+/// Bytes of a string made by `string_from_bytes`.
+pub fn string_to_bytes(s: &str) -> Cow<'_, [u8]> {
+    if !s.chars().any(is_raw) {
+        return Cow::Borrowed(s.as_bytes());
+    }
+    let mut out = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        match (c as u32).checked_sub(RAW_BYTES) {
+            Some(b) => out.push(b as u8),
+            None => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// A string made by `string_from_bytes` as shown to the user: bytes that
+/// are not UTF-8 become `�`.
+pub fn lossy(s: &str) -> Cow<'_, str> {
+    match string_to_bytes(s) {
+        Cow::Borrowed(_) => Cow::Borrowed(s),
+        Cow::Owned(bytes) => Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()),
+    }
+}
+
+/// `lossy` of every string.
+pub fn lossy_all(strings: &[String]) -> Vec<String> {
+    strings.iter().map(|s| lossy(s).into_owned()).collect()
+}
+
+/// Ordered list of key/value pairs. Duplicated keys are kept as they are.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KeyValues(pub Vec<(String, VdfValue)>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VdfValue {
+    Map(KeyValues),
+    String(String),
+    /// Stored as in the file; Steam uses it for both signed and unsigned values.
+    Int32(u32),
+    Float(f32),
+    Pointer(u32),
+    /// UTF-16 string.
+    WString(String),
+    Color(u32),
+    UInt64(u64),
+    Int64(i64),
+}
+
+impl VdfValue {
+    pub fn type_byte(&self) -> u8 {
+        match self {
+            VdfValue::Map(_) => TYPE_MAP,
+            VdfValue::String(_) => TYPE_STRING,
+            VdfValue::Int32(_) => TYPE_INT32,
+            VdfValue::Float(_) => TYPE_FLOAT,
+            VdfValue::Pointer(_) => TYPE_POINTER,
+            VdfValue::WString(_) => TYPE_WSTRING,
+            VdfValue::Color(_) => TYPE_COLOR,
+            VdfValue::UInt64(_) => TYPE_UINT64,
+            VdfValue::Int64(_) => TYPE_INT64,
+        }
+    }
+
+    /// Name used by the `$type` tag of JSON and the hint comments of text
+    /// output. Strings and maps need none.
+    pub fn type_name(&self) -> Option<&'static str> {
+        match self {
+            VdfValue::Map(_) | VdfValue::String(_) => None,
+            VdfValue::Int32(_) => Some("int32"),
+            VdfValue::Float(_) => Some("float"),
+            VdfValue::Pointer(_) => Some("pointer"),
+            VdfValue::WString(_) => Some("wstring"),
+            VdfValue::Color(_) => Some("color"),
+            VdfValue::UInt64(_) => Some("uint64"),
+            VdfValue::Int64(_) => Some("int64"),
+        }
+    }
+
+    /// Value as text, the way the text format writes it.
+    fn scalar_text(&self) -> String {
+        match self {
+            VdfValue::Map(_) => String::new(),
+            VdfValue::String(s) | VdfValue::WString(s) => s.clone(),
+            VdfValue::Int32(n) | VdfValue::Pointer(n) | VdfValue::Color(n) => n.to_string(),
+            VdfValue::Float(f) => f.to_string(),
+            VdfValue::UInt64(n) => n.to_string(),
+            VdfValue::Int64(n) => n.to_string(),
+        }
+    }
+
+    /// Parse `text` as a value of the type called `type_name` (see `type_name`),
+    /// or as a string when there is no type.
+    fn from_text(text: String, type_name: Option<&str>) -> Result<Self, String> {
+        let bad = |e: &dyn std::fmt::Display| {
+            format!(
+                "Invalid {} value \"{}\": {}",
+                type_name.unwrap_or("string"),
+                text,
+                e
+            )
+        };
+        Ok(match type_name {
+            None | Some("string") => VdfValue::String(text),
+            Some("wstring") => VdfValue::WString(text),
+            Some("int32") => VdfValue::Int32(parse_int32(&text).map_err(|e| bad(&e))?),
+            Some("pointer") => VdfValue::Pointer(parse_int32(&text).map_err(|e| bad(&e))?),
+            Some("color") => VdfValue::Color(parse_int32(&text).map_err(|e| bad(&e))?),
+            Some("float") => VdfValue::Float(text.parse().map_err(|e| bad(&e))?),
+            Some("uint64") => VdfValue::UInt64(text.parse().map_err(|e| bad(&e))?),
+            Some("int64") => VdfValue::Int64(text.parse().map_err(|e| bad(&e))?),
+            Some(t) => return Err(format!("Unknown type \"{}\".", t)),
+        })
+    }
+}
+
+/// 32 bit values are written unsigned, but negative ones are accepted too.
+fn parse_int32(text: &str) -> Result<u32, std::num::ParseIntError> {
+    text.parse::<u32>()
+        .or_else(|_| text.parse::<i32>().map(|n| n as u32))
+}
+
+/// Formats `convert` reads and writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VdfFormat {
+    Binary,
+    Text,
+    Json,
+}
+
+impl VdfFormat {
+    /// Guess the format of `buffer` from its first bytes.
+    pub fn sniff(buffer: &[u8]) -> Self {
+        match buffer.first() {
+            Some(b) if *b <= TYPE_UINT64 => VdfFormat::Binary,
+            _ => match buffer
+                .strip_prefix(b"\xEF\xBB\xBF")
+                .unwrap_or(buffer)
+                .iter()
+                .find(|b| !b.is_ascii_whitespace())
+            {
+                Some(b'{') | Some(b'[') => VdfFormat::Json,
+                _ => VdfFormat::Text,
+            },
+        }
+    }
+}
+
+impl KeyValues {
+    pub fn read(buffer: &[u8], format: VdfFormat) -> Result<Self, String> {
+        match format {
+            VdfFormat::Binary => Self::from_binary(buffer),
+            VdfFormat::Text => Self::from_text(
+                std::str::from_utf8(buffer).map_err(|e| format!("Invalid UTF-8 text: {}", e))?,
+            ),
+            VdfFormat::Json => serde_json::from_slice(buffer).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn write(&self, format: VdfFormat) -> Result<Vec<u8>, String> {
+        match format {
+            VdfFormat::Binary => self.to_binary(),
+            VdfFormat::Text => Ok(self.to_text().into_bytes()),
+            VdfFormat::Json => serde_json::to_vec_pretty(self)
+                .map(|mut v| {
+                    v.push(b'\n');
+                    v
+                })
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Parse a binary file: the items of the root map followed by its end byte.
+    pub fn from_binary(buffer: &[u8]) -> Result<Self, String> {
+        let mut reader = BinaryReader { buffer, pos: 0 };
+        let root = reader.read_items(0)?;
+        if reader.pos != buffer.len() {
+            return Err(format!(
+                "Unexpected data after the end of the root map at offset 0x{:x}.",
+                reader.pos
+            ));
+        }
+        Ok(root)
+    }
+
+    /// Parse the items of a map whose key ends just before `offset`, up to
+    /// and including its end byte. Returns them with the offset following the map.
+    pub fn from_binary_at(buffer: &[u8], offset: usize) -> Result<(Self, usize), String> {
+        let mut reader = BinaryReader {
+            buffer,
+            pos: offset,
+        };
+        let items = reader.read_items(0)?;
+        Ok((items, reader.pos))
+    }
+
+    pub fn to_binary(&self) -> Result<Vec<u8>, String> {
+        let mut out = vec![];
+        write_binary_items(self, &mut out)?;
+        Ok(out)
+    }
+
+    /// Valve's text KeyValues. Types other than strings and maps are kept in
+    /// a `// type` comment after the value.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        write_text_items(self, 0, &mut out);
+        out
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut parser = TextParser {
+            chars: text.chars().collect(),
+            pos: 0,
+            line: 1,
+        };
+        let root = parser.read_items(0)?;
+        Ok(root)
+    }
+}
+
+struct BinaryReader<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl BinaryReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let slice = self.buffer.get(self.pos..self.pos + len).ok_or(format!(
+            "Unexpected end of file at offset 0x{:x}, {} more bytes expected.",
+            self.pos, len
+        ))?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut a = [0; N];
+        a.copy_from_slice(self.take(N)?);
+        Ok(a)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        let len = self
+            .buffer
+            .get(start..)
+            .and_then(|rest| rest.iter().position(|b| *b == 0))
+            .ok_or(format!("Unterminated string at offset 0x{:x}.", start))?;
+        let bytes = self.take(len + 1)?;
+        Ok(string_from_bytes(&bytes[..len]))
+    }
+
+    fn wstring(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        let mut units = vec![];
+        loop {
+            match u16::from_le_bytes(self.array()?) {
+                0 => break,
+                u => units.push(u),
+            }
+        }
+        String::from_utf16(&units)
+            .map_err(|e| format!("Invalid UTF-16 string at offset 0x{:x}: {}", start, e))
+    }
+
+    fn read_items(&mut self, depth: usize) -> Result<KeyValues, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!(
+                "Maps nested deeper than {} levels at offset 0x{:x}.",
+                MAX_DEPTH, self.pos
+            ));
+        }
+        let mut items = vec![];
+        loop {
+            let offset = self.pos;
+            let type_byte = self.array::<1>()?[0];
+            if type_byte == TYPE_MAP_END {
+                return Ok(KeyValues(items));
+            }
+            let key = self.string()?;
+            let value = match type_byte {
+                TYPE_MAP => VdfValue::Map(self.read_items(depth + 1)?),
+                TYPE_STRING => VdfValue::String(self.string()?),
+                TYPE_INT32 => VdfValue::Int32(u32::from_le_bytes(self.array()?)),
+                TYPE_FLOAT => VdfValue::Float(f32::from_le_bytes(self.array()?)),
+                TYPE_POINTER => VdfValue::Pointer(u32::from_le_bytes(self.array()?)),
+                TYPE_WSTRING => VdfValue::WString(self.wstring()?),
+                TYPE_COLOR => VdfValue::Color(u32::from_le_bytes(self.array()?)),
+                TYPE_UINT64 => VdfValue::UInt64(u64::from_le_bytes(self.array()?)),
+                TYPE_INT64 => VdfValue::Int64(i64::from_le_bytes(self.array()?)),
+                t => {
+                    return Err(format!(
+                        "Unknown type byte 0x{:02x} at offset 0x{:x}.",
+                        t, offset
+                    ))
+                }
+            };
+            items.push((key, value));
+        }
+    }
+}
+
+fn write_binary_string(s: &str, out: &mut Vec<u8>) -> Result<(), String> {
+    let bytes = string_to_bytes(s);
+    if bytes.contains(&0) {
+        return Err(format!("String {:?} contains a NUL character.", lossy(s)));
+    }
+    out.extend_from_slice(&bytes);
+    out.push(0);
+    Ok(())
+}
+
+fn write_binary_items(items: &KeyValues, out: &mut Vec<u8>) -> Result<(), String> {
+    for (key, value) in &items.0 {
+        out.push(value.type_byte());
+        write_binary_string(key, out)?;
+        match value {
+            VdfValue::Map(m) => write_binary_items(m, out)?,
+            VdfValue::String(s) => write_binary_string(s, out)?,
+            VdfValue::Int32(n) | VdfValue::Pointer(n) | VdfValue::Color(n) => {
+                out.extend_from_slice(&n.to_le_bytes())
+            }
+            VdfValue::Float(f) => out.extend_from_slice(&f.to_le_bytes()),
+            VdfValue::WString(s) => {
+                for u in s.encode_utf16().chain(std::iter::once(0)) {
+                    out.extend_from_slice(&u.to_le_bytes());
+                }
+            }
+            VdfValue::UInt64(n) => out.extend_from_slice(&n.to_le_bytes()),
+            VdfValue::Int64(n) => out.extend_from_slice(&n.to_le_bytes()),
+        }
+    }
+    out.push(TYPE_MAP_END);
+    Ok(())
+}
+
+fn quote_text(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn write_text_items(items: &KeyValues, depth: usize, out: &mut String) {
+    let indent = "\t".repeat(depth);
+    for (key, value) in &items.0 {
+        match value {
+            VdfValue::Map(m) => {
+                let _ = writeln!(out, "{}{}", indent, quote_text(key));
+                let _ = writeln!(out, "{}{{", indent);
+                write_text_items(m, depth + 1, out);
+                let _ = writeln!(out, "{}}}", indent);
+            }
+            _ => {
+                let _ = write!(
+                    out,
+                    "{}{}\t\t{}",
+                    indent,
+                    quote_text(key),
+                    quote_text(&value.scalar_text())
+                );
+                if let Some(t) = value.type_name() {
+                    let _ = write!(out, "\t// {}", t);
+                }
+                out.push('\n');
+            }
+        }
+    }
+}
+
+struct TextParser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+impl TextParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("Line {}: {}", self.line, message)
+    }
+
+    /// Skip blanks and comments up to the next token.
+    fn skip_blank(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == '\u{feff}' {
+                self.bump();
+            } else if c == '/' && self.chars.get(self.pos + 1) == Some(&'/') {
+                self.rest_of_line();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn rest_of_line(&mut self) -> String {
+        let mut s = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            s.push(c);
+            self.bump();
+        }
+        s
+    }
+
+    /// `// type` comment on the same line as the value just read.
+    fn type_hint(&mut self) -> Option<String> {
+        let mut p = self.pos;
+        while matches!(self.chars.get(p), Some(' ') | Some('\t') | Some('\r')) {
+            p += 1;
+        }
+        if self.chars.get(p) == Some(&'/') && self.chars.get(p + 1) == Some(&'/') {
+            self.pos = p;
+            let comment = self.rest_of_line();
+            return Some(comment.trim_start_matches('/').trim().to_owned());
+        }
+        None
+    }
+
+    fn token(&mut self) -> Result<Option<Token>, String> {
+        self.skip_blank();
+        let Some(c) = self.bump() else {
+            return Ok(None);
+        };
+        Ok(Some(match c {
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => break,
+                        Some('\\') => match self.bump() {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some('r') => s.push('\r'),
+                            Some(c) => s.push(c),
+                            None => return Err(self.error("Unterminated string.")),
+                        },
+                        Some(c) => s.push(c),
+                        None => return Err(self.error("Unterminated string.")),
+                    }
+                }
+                Token::Str(s)
+            }
+            c => {
+                let mut s = String::from(c);
+                while let Some(c) = self.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                        break;
+                    }
+                    s.push(c);
+                    self.bump();
+                }
+                Token::Str(s)
+            }
+        }))
+    }
+
+    fn read_items(&mut self, depth: usize) -> Result<KeyValues, String> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(&format!("Maps nested deeper than {} levels.", MAX_DEPTH)));
+        }
+        let nested = depth > 0;
+        let mut items = vec![];
+        loop {
+            let key = match self.token()? {
+                Some(Token::Str(key)) => key,
+                Some(Token::Close) if nested => return Ok(KeyValues(items)),
+                None if !nested => return Ok(KeyValues(items)),
+                None => return Err(self.error("Unexpected end of file, \"}\" expected.")),
+                Some(_) => return Err(self.error("Key expected.")),
+            };
+            let value = match self.token()? {
+                Some(Token::Open) => VdfValue::Map(self.read_items(depth + 1)?),
+                Some(Token::Str(text)) => {
+                    let hint = self.type_hint();
+                    VdfValue::from_text(text, hint.as_deref()).map_err(|e| self.error(&e))?
+                }
+                _ => return Err(self.error(&format!("Value expected for key \"{}\".", key))),
+            };
+            items.push((key, value));
+        }
+    }
+}
+
+/// Items of a map as a JSON object, never tagged.
+struct JsonItems<'a>(&'a KeyValues);
+
+impl Serialize for JsonItems<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0 .0.len()))?;
+        for (key, value) in &self.0 .0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// A map whose keys are just `$type` and `value` would read back as a typed
+/// value: it is tagged itself, `{"$type": "map", "value": {...}}`.
+impl Serialize for KeyValues {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let keys: Vec<&str> = self.0.iter().map(|(k, _)| k.as_str()).collect();
+        if keys == ["$type", "value"] || keys == ["value", "$type"] {
+            let mut map = serializer.serialize_map(Some(2))?;
+            map.serialize_entry("$type", "map")?;
+            map.serialize_entry("value", &JsonItems(self))?;
+            map.end()
+        } else {
+            JsonItems(self).serialize(serializer)
+        }
+    }
+}
+
+/// Strings and maps are plain JSON values, 32 bit integers are numbers and
+/// the other types are tagged: `{"$type": "uint64", "value": 1}`.
+impl Serialize for VdfValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            VdfValue::Map(m) => m.serialize(serializer),
+            VdfValue::String(s) => serializer.serialize_str(s),
+            VdfValue::Int32(n) => serializer.serialize_u32(*n),
+            other => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("$type", &other.type_name())?;
+                match other {
+                    VdfValue::Float(f) => map.serialize_entry("value", f)?,
+                    VdfValue::Pointer(n) | VdfValue::Color(n) => map.serialize_entry("value", n)?,
+                    VdfValue::WString(s) => map.serialize_entry("value", s)?,
+                    VdfValue::UInt64(n) => map.serialize_entry("value", n)?,
+                    VdfValue::Int64(n) => map.serialize_entry("value", n)?,
+                    _ => unreachable!(),
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/// JSON value read in document order, before types are applied.
+enum JsonNode {
+    Str(String),
+    Number(serde_json::Number),
+    Map(Vec<(String, JsonNode)>),
+}
+
+impl<'de> Deserialize<'de> for JsonNode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = JsonNode;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a string, a number or an object")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<JsonNode, E> {
+                Ok(JsonNode::Str(v.to_owned()))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<JsonNode, E> {
+                Ok(JsonNode::Number(v.into()))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<JsonNode, E> {
+                Ok(JsonNode::Number(v.into()))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<JsonNode, E> {
+                serde_json::Number::from_f64(v)
+                    .map(JsonNode::Number)
+                    .ok_or_else(|| E::custom("invalid number"))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, _: A) -> Result<JsonNode, A::Error> {
+                Err(de::Error::custom("arrays cannot be stored in a VDF file"))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<JsonNode, A::Error> {
+                let mut items = vec![];
+                while let Some((k, v)) = access.next_entry::<String, JsonNode>()? {
+                    items.push((k, v));
+                }
+                Ok(JsonNode::Map(items))
+            }
+        }
+
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+impl JsonNode {
+    fn into_value(self, depth: usize) -> Result<VdfValue, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("Objects nested deeper than {} levels.", MAX_DEPTH));
+        }
+        match self {
+            JsonNode::Str(s) => Ok(VdfValue::String(s)),
+            JsonNode::Number(n) => VdfValue::from_text(n.to_string(), Some("int32")),
+            JsonNode::Map(mut items) => {
+                let tagged = items.len() == 2
+                    && items
+                        .iter()
+                        .any(|(k, v)| k == "$type" && matches!(v, JsonNode::Str(_)))
+                    && items.iter().any(|(k, _)| k == "value");
+                if !tagged {
+                    return Self::into_map(items, depth);
+                }
+                items.sort_by_key(|(k, _)| k != "$type");
+                let mut items = items.into_iter();
+                let (Some((_, JsonNode::Str(type_name))), Some((_, value))) =
+                    (items.next(), items.next())
+                else {
+                    unreachable!()
+                };
+                match (type_name.as_str(), value) {
+                    ("map", JsonNode::Map(items)) => Self::into_map(items, depth),
+                    (_, JsonNode::Map(_)) => Err("Typed values cannot be objects.".to_owned()),
+                    (_, JsonNode::Str(s)) => VdfValue::from_text(s, Some(&type_name)),
+                    (_, JsonNode::Number(n)) => {
+                        VdfValue::from_text(n.to_string(), Some(&type_name))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Map of `items`, whatever their keys.
+    fn into_map(items: Vec<(String, JsonNode)>, depth: usize) -> Result<VdfValue, String> {
+        Ok(VdfValue::Map(KeyValues(
+            items
+                .into_iter()
+                .map(|(k, v)| Ok((k, v.into_value(depth + 1)?)))
+                .collect::<Result<_, String>>()?,
+        )))
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyValues {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match JsonNode::deserialize(deserializer)?.into_value(0) {
+            Ok(VdfValue::Map(m)) => Ok(m),
+            Ok(_) => Err(de::Error::custom("the root must be an object")),
+            Err(e) => Err(de::Error::custom(e)),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Golden files: every `tests/corpus/<name>.vdf` must load to the entries of
+//! `tests/corpus/<name>.json` and store back to a file that loads the same.
+//! The expected JSON is never written by the tests: it is what the C++
+//! editor lists, with the known differences of `tests/corpus/README.md`, and
+//! `corpus_agrees_with_cpp_editor` checks it against a build of the C++
+//! editor.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::{Map, Value};
+use steam_shortcuts_editor::shortcuts::{Shortcuts, SHORTCUT_PROP_INFO};
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Entries as `list --json` prints them.
+fn to_json(scs: &Shortcuts) -> Vec<Value> {
+    scs.iter().map(|sc| Value::Object(sc.to_json())).collect()
+}
+
+/// What differs between two entry lists, one line per field.
+fn differences(expected: &[Value], actual: &[Value]) -> Vec<String> {
+    if expected.len() != actual.len() {
+        return vec![format!(
+            "{} entries expected, {} found",
+            expected.len(),
+            actual.len()
+        )];
+    }
+    let mut out = vec![];
+    for (n, (e, a)) in expected.iter().zip(actual).enumerate() {
+        let (Some(e), Some(a)) = (e.as_object(), a.as_object()) else {
+            out.push(format!("[{}] is not an object", n));
+            continue;
+        };
+        let mut keys: Vec<&String> = e.keys().chain(a.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            if e.get(key) != a.get(key) {
+                out.push(format!(
+                    "[{}].{}: expected {}, found {}",
+                    n,
+                    key,
+                    e.get(key).map_or("nothing".to_owned(), Value::to_string),
+                    a.get(key).map_or("nothing".to_owned(), Value::to_string),
+                ));
+            }
+        }
+    }
+    out
+}
+
+fn load(path: &Path) -> Result<Shortcuts, String> {
+    let buffer = fs::read(path).map_err(|e| e.to_string())?;
+    Shortcuts::parse(&buffer, &mut 0)
+}
+
+fn read_json(path: &Path) -> Result<Vec<Value>, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
+/// Problems found with one corpus sample.
+fn check(vdf: &Path) -> Vec<String> {
+    let scs = match load(vdf) {
+        Ok(scs) => scs,
+        Err(e) => return vec![format!("cannot be loaded: {}", e)],
+    };
+    let actual = to_json(&scs);
+
+    let expected_path = vdf.with_extension("json");
+    let mut problems = match read_json(&expected_path) {
+        Ok(expected) => differences(&expected, &actual),
+        Err(e) => vec![format!("{}: {}", expected_path.display(), e)],
+    };
+
+    // Storing fills the missing known fields with their defaults, every
+    // field that was read must come back unchanged.
+    let mut stored = vec![];
+    if let Err(e) = scs.store_into(&mut stored) {
+        problems.push(format!("cannot be stored: {}", e));
+        return problems;
+    }
+    match Shortcuts::parse(&stored, &mut 0) {
+        Ok(reloaded) => {
+            let reloaded = to_json(&reloaded);
+            let kept: Vec<Value> = reloaded
+                .iter()
+                .zip(&actual)
+                .map(|(r, a)| match (r, a) {
+                    (Value::Object(r), Value::Object(a)) => Value::Object(
+                        r.iter()
+                            .filter(|(k, _)| a.contains_key(*k))
+                            .map(|(k, v)| (k.clone(), v.clone()))
+                            .collect(),
+                    ),
+                    _ => r.clone(),
+                })
+                .collect();
+            if reloaded.len() != actual.len() {
+                problems.push(format!(
+                    "after store: {} entries expected, {} found",
+                    actual.len(),
+                    reloaded.len()
+                ));
+            } else {
+                problems.extend(
+                    differences(&actual, &kept)
+                        .into_iter()
+                        .map(|d| format!("after store: {}", d)),
+                );
+            }
+        }
+        Err(e) => problems.push(format!("cannot be loaded after store: {}", e)),
+    }
+    problems
+}
+
+fn samples() -> Vec<PathBuf> {
+    let dir = manifest_dir().join("tests/corpus");
+    let mut samples: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "vdf"))
+        .collect();
+    samples.sort();
+    assert!(!samples.is_empty(), "no sample in {}", dir.display());
+    samples
+}
+
+/// `check` every sample, one line per problem.
+fn check_all(check: impl Fn(&Path) -> Vec<String>) -> Vec<String> {
+    samples()
+        .iter()
+        .flat_map(|vdf| {
+            let name = vdf.file_name().unwrap().to_string_lossy().into_owned();
+            check(vdf)
+                .into_iter()
+                .map(move |p| format!("{}: {}", name, p))
+        })
+        .collect()
+}
+
+#[test]
+fn corpus() {
+    let failures = check_all(check);
+    assert!(failures.is_empty(), "\n{}\n", failures.join("\n"));
+}
+
+/// Expected entries as the C++ editor lists them: unknown keys are dropped,
+/// missing known fields get their default value and tags are compared
+/// whatever their order (C++ sorts them by their string key).
+fn as_cpp_lists(entries: &[Value]) -> Vec<Value> {
+    entries
+        .iter()
+        .map(|entry| {
+            let Some(entry) = entry.as_object() else {
+                return entry.clone();
+            };
+            let mut out = Map::new();
+            for info in SHORTCUT_PROP_INFO.iter() {
+                let value = match entry.get(info.switchname) {
+                    Some(value) => value.clone(),
+                    None if info.switchname == "tags" => continue,
+                    None => info.type_default.to_json().unwrap(),
+                };
+                out.insert(info.switchname.to_owned(), value);
+            }
+            if let Some(Value::Array(tags)) = out.get_mut("tags") {
+                tags.sort_by_key(Value::to_string);
+            }
+            Value::Object(out)
+        })
+        .collect()
+}
+
+/// `list --json` of the C++ editor at `editor`. It only escapes `"` in the
+/// strings that are not tags, so a lone `\` is escaped here, and it prints
+/// bytes that are not UTF-8 as they are, replaced here like the Rust editor
+/// shows them.
+fn cpp_list(editor: &Path, vdf: &Path) -> Result<Vec<Value>, String> {
+    let output = Command::new(editor)
+        .arg("list")
+        .arg(vdf)
+        .arg("--json")
+        .output()
+        .map_err(|e| format!("cannot run {}: {}", editor.display(), e))?;
+    if !output.status.success() {
+        return Err(format!("C++ editor failed: {}", output.status));
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        escaped.push(c);
+        if c == '\\' {
+            match chars.peek() {
+                Some('"') | Some('\\') => escaped.push(chars.next().unwrap()),
+                _ => escaped.push('\\'),
+            }
+        }
+    }
+    serde_json::from_str(&escaped).map_err(|e| format!("C++ output is not JSON: {}", e))
+}
+
+/// Needs a build of the C++ editor (see `cpp/README.md`):
+/// `CPP_EDITOR=../cpp/build/Debug/steam-shortcuts-editor cargo test --test conformance -- --ignored`
+#[test]
+#[ignore = "needs the C++ editor, set CPP_EDITOR to its path"]
+fn corpus_agrees_with_cpp_editor() {
+    let editor = PathBuf::from(std::env::var_os("CPP_EDITOR").expect("CPP_EDITOR is not set"));
+    let failures = check_all(|vdf| {
+        let expected = match read_json(&vdf.with_extension("json")) {
+            Ok(expected) => as_cpp_lists(&expected),
+            Err(e) => return vec![e],
+        };
+        match cpp_list(&editor, vdf) {
+            Ok(cpp) => differences(&as_cpp_lists(&cpp), &expected),
+            Err(e) => vec![e],
+        }
+    });
+    assert!(failures.is_empty(), "\n{}\n", failures.join("\n"));
+}
+
+/// `examples/cpp_output.json` is what the C++ editor lists for
+/// `examples/shortcuts_1.vdf`.
+#[test]
+fn agrees_with_cpp_editor() {
+    let examples = manifest_dir().join("../examples");
+    let scs = load(&examples.join("shortcuts_1.vdf")).unwrap();
+    let expected = read_json(&examples.join("cpp_output.json")).unwrap();
+    let failures = differences(&expected, &to_json(&scs));
+    assert!(failures.is_empty(), "\n{}\n", failures.join("\n"));
+}
@@ -0,0 +1,154 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! `--dry-run` through the command line: nothing is written, and the exit
+//! code is 3 exactly when the stored entries would change.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const EXIT_PENDING_CHANGES: i32 = 3;
+
+/// Copy of a corpus sample in its own folder, removed on drop.
+struct Sample(PathBuf);
+
+impl Sample {
+    fn new(test: &str, name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "steam-shortcuts-editor-dry-run-{}-{}",
+            test,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+        fs::copy(corpus.join(name), dir.join("shortcuts.vdf")).unwrap();
+        Self(dir)
+    }
+
+    fn vdf(&self) -> String {
+        self.0.join("shortcuts.vdf").to_str().unwrap().to_owned()
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        fs::read(self.vdf()).unwrap()
+    }
+}
+
+impl Drop for Sample {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Exit code and standard output of the editor.
+fn run(args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_steam-shortcuts-editor"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn unchanged_entries_exit_with_0() {
+    let sample = Sample::new("unchanged", "sparse_indices.vdf");
+    let vdf = sample.vdf();
+    let (code, out) = run(&[
+        "edit",
+        &vdf,
+        "--idx",
+        "3",
+        "--key",
+        "app_name",
+        "--val",
+        "Fourth",
+        "--out",
+        &vdf,
+        "--dry-run",
+    ]);
+    assert_eq!(code, 0, "{}", out);
+    assert!(out.ends_with("No changes.\n"), "{}", out);
+}
+
+#[test]
+fn changes_exit_with_3_and_write_nothing() {
+    let sample = Sample::new("changes", "sparse_indices.vdf");
+    let vdf = sample.vdf();
+    let before = sample.bytes();
+    let (code, out) = run(&[
+        "edit",
+        &vdf,
+        "--idx",
+        "3",
+        "--key",
+        "app_name",
+        "--val",
+        "Other",
+        "--out",
+        &vdf,
+        "--force",
+        "--dry-run",
+    ]);
+    assert_eq!(code, EXIT_PENDING_CHANGES, "{}", out);
+    assert!(
+        out.contains("~ [3] \"Other\"\n    app_name: \"Fourth\" → \"Other\"\n"),
+        "{}",
+        out
+    );
+    assert_eq!(sample.bytes(), before);
+}
+
+#[test]
+fn renumbering_only_is_a_change() {
+    let sample = Sample::new("renumber", "sparse_indices.vdf");
+    let vdf = sample.vdf();
+    let before = sample.bytes();
+    let empty = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus/empty.vdf");
+    // Merging renumbers the entries from 0, no field changes.
+    let (code, out) = run(&[
+        "merge",
+        &vdf,
+        empty.to_str().unwrap(),
+        "--out",
+        &vdf,
+        "--force",
+        "--dry-run",
+    ]);
+    assert_eq!(code, EXIT_PENDING_CHANGES, "{}", out);
+    assert!(
+        out.contains("No field changes, but the file would be written"),
+        "{}",
+        out
+    );
+    assert_eq!(sample.bytes(), before);
+}
+
+#[test]
+fn new_files_are_not_created() {
+    let sample = Sample::new("new", "minimal.vdf");
+    let out_path = sample.0.join("new.vdf");
+    let out_path = out_path.to_str().unwrap();
+    let (code, out) = run(&[
+        "merge",
+        &sample.vdf(),
+        &sample.vdf(),
+        "--out",
+        out_path,
+        "--dry-run",
+    ]);
+    assert_eq!(code, EXIT_PENDING_CHANGES, "{}", out);
+    assert!(out.contains("+ [0] \"Minimal\""), "{}", out);
+    assert!(!Path::new(out_path).exists());
+}
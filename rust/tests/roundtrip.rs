@@ -0,0 +1,260 @@
+/*
+ * Copyright (c) 2022, Magius(CHE)
+ *
+ * This software is provided "as-is", without any express or implied warranty. In no event
+ * will the authors be held liable for any damages arising from the use of this software.
+ * Read the LICENSE file for more details.
+ *
+ * @author: Magius(CHE) - magiusche@magius.it
+ */
+
+//! Property tests of the codecs: whatever is stored loads back the same, and
+//! no input makes a reader panic.
+
+use proptest::collection::{btree_map, vec};
+use proptest::prelude::*;
+use steam_shortcuts_editor::shortcuts::{Shortcut, ShortcutProp, Shortcuts, SHORTCUT_PROP_INFO};
+use steam_shortcuts_editor::vdf::{self, KeyValues, VdfFormat, VdfValue};
+
+/// Any text a vdf string can hold: everything but NUL, and the characters
+/// `vdf::string_from_bytes` uses for bytes that are not UTF-8.
+fn text() -> impl Strategy<Value = String> {
+    "[^\0\u{10FF00}-\u{10FFFF}]{0,24}"
+}
+
+/// Bytes that are not valid UTF-8, kept as `ShortcutProp::Bytes`.
+fn invalid_utf8() -> impl Strategy<Value = Vec<u8>> {
+    (vec(1u8..=255, 0..12), vec(1u8..=255, 0..12)).prop_map(|(a, b)| [a, vec![0xFF], b].concat())
+}
+
+/// A tag or a generic vdf string, UTF-8 or not.
+fn raw_text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => text(),
+        1 => invalid_utf8().prop_map(|b| vdf::string_from_bytes(&b)),
+    ]
+}
+
+fn string_prop() -> impl Strategy<Value = ShortcutProp> {
+    prop_oneof![
+        4 => text().prop_map(ShortcutProp::String),
+        1 => invalid_utf8().prop_map(ShortcutProp::Bytes),
+    ]
+}
+
+/// Keys unknown to the editor. Loading lowercases them. Some end with bytes
+/// that are never UTF-8.
+fn extra_key() -> impl Strategy<Value = String> {
+    ("[a-z][a-z0-9_]{0,11}", vec(0xF8u8..=0xFF, 0..3))
+        .prop_map(|(k, raw)| vdf::string_from_bytes(&[k.as_bytes(), &raw].concat()))
+        .prop_filter("known key", |k| {
+            !SHORTCUT_PROP_INFO
+                .iter()
+                .any(|p| p.switchname == k || p.name.eq_ignore_ascii_case(k))
+        })
+}
+
+fn extra_prop() -> impl Strategy<Value = ShortcutProp> {
+    prop_oneof![
+        any::<u32>().prop_map(ShortcutProp::UInt32),
+        string_prop(),
+        vec(raw_text(), 0..4).prop_map(ShortcutProp::Strings),
+    ]
+}
+
+/// An entry with any subset of the known props, with the right types, and
+/// a few unknown ones.
+fn shortcut() -> impl Strategy<Value = Shortcut> {
+    let known: Vec<BoxedStrategy<Option<ShortcutProp>>> = SHORTCUT_PROP_INFO
+        .iter()
+        .filter(|p| p.switchname != "index")
+        .map(|p| match p.type_default {
+            ShortcutProp::UInt32(_) => {
+                proptest::option::of(any::<u32>().prop_map(ShortcutProp::UInt32)).boxed()
+            }
+            ShortcutProp::Strings(_) => {
+                proptest::option::of(vec(raw_text(), 0..4).prop_map(ShortcutProp::Strings)).boxed()
+            }
+            _ => proptest::option::of(string_prop()).boxed(),
+        })
+        .collect();
+    (known, btree_map(extra_key(), extra_prop(), 0..3)).prop_map(|(known, extra)| {
+        let mut sc = Shortcut::empty();
+        let names = SHORTCUT_PROP_INFO
+            .iter()
+            .filter(|p| p.switchname != "index");
+        for (info, prop) in names.zip(known) {
+            if let Some(prop) = prop {
+                sc.props.insert(info.switchname.to_owned(), prop);
+            }
+        }
+        sc.props.extend(extra);
+        sc
+    })
+}
+
+fn store(scs: &Shortcuts) -> Vec<u8> {
+    let mut out = vec![];
+    scs.store_into(&mut out).expect("store");
+    out
+}
+
+/// Any key, often the ones JSON uses to tag typed values.
+fn key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => "[^\0\u{10FF00}-\u{10FFFF}]{1,9}",
+        1 => invalid_utf8().prop_map(|b| vdf::string_from_bytes(&b)),
+        1 => Just("$type".to_owned()),
+        1 => Just("value".to_owned()),
+    ]
+}
+
+/// Typed VDF value, without NaN floats (they never compare equal).
+fn vdf_value() -> impl Strategy<Value = VdfValue> {
+    let leaf = prop_oneof![
+        raw_text().prop_map(VdfValue::String),
+        any::<u32>().prop_map(VdfValue::Int32),
+        any::<f32>()
+            .prop_filter("NaN", |f| f.is_finite())
+            .prop_map(VdfValue::Float),
+        any::<u32>().prop_map(VdfValue::Pointer),
+        text().prop_map(VdfValue::WString),
+        any::<u32>().prop_map(VdfValue::Color),
+        any::<u64>().prop_map(VdfValue::UInt64),
+        any::<i64>().prop_map(VdfValue::Int64),
+    ];
+    leaf.prop_recursive(4, 32, 6, |inner| {
+        prop_oneof![
+            4 => vec((key(), inner.clone()), 0..6)
+                .prop_map(|items| VdfValue::Map(KeyValues(items))),
+            // Maps looking like the typed values of JSON.
+            1 => (prop_oneof![Just("uint64".to_owned()), text()], inner).prop_map(
+                |(type_name, value)| {
+                    VdfValue::Map(KeyValues(vec![
+                        ("$type".to_owned(), VdfValue::String(type_name)),
+                        ("value".to_owned(), value),
+                    ]))
+                }
+            ),
+        ]
+    })
+}
+
+fn key_values() -> impl Strategy<Value = KeyValues> {
+    vec((key(), vdf_value()), 0..6).prop_map(KeyValues)
+}
+
+proptest! {
+    #[test]
+    fn store_then_load_gives_the_same_shortcuts(entries in vec(shortcut(), 0..8)) {
+        let mut scs = Shortcuts::empty();
+        for sc in entries {
+            scs.push(sc);
+        }
+        let loaded = Shortcuts::parse(&store(&scs), &mut 0).expect("load");
+
+        prop_assert_eq!(loaded.len(), scs.len());
+        for (index, expected) in (0..scs.len() as u32).map(|i| (i, scs.at(&i).unwrap())) {
+            let actual = loaded.at(&index).expect("entry");
+            // Missing known props are written with their default value.
+            for info in SHORTCUT_PROP_INFO.iter() {
+                let expected = expected.props.get(info.switchname).unwrap_or(&info.type_default);
+                prop_assert_eq!(actual.props.get(info.switchname), Some(expected), "{}", info.switchname);
+            }
+            prop_assert_eq!(actual.extra_props(), expected.extra_props());
+        }
+    }
+
+    #[test]
+    fn store_is_stable(entries in vec(shortcut(), 0..8)) {
+        let mut scs = Shortcuts::empty();
+        for sc in entries {
+            scs.push(sc);
+        }
+        let once = store(&scs);
+        let twice = store(&Shortcuts::parse(&once, &mut 0).expect("load"));
+        prop_assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn vdf_round_trips_in_every_format(kv in key_values()) {
+        for format in [VdfFormat::Binary, VdfFormat::Text, VdfFormat::Json] {
+            let bytes = kv.write(format).expect("write");
+            prop_assert_eq!(&KeyValues::read(&bytes, format).expect("read"), &kv, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn binary_vdf_is_rewritten_byte_for_byte(kv in key_values()) {
+        let bytes = kv.to_binary().expect("write");
+        let read = KeyValues::from_binary(&bytes).expect("read");
+        prop_assert_eq!(read.to_binary().expect("write"), bytes);
+    }
+
+    #[test]
+    fn readers_never_panic(data in vec(any::<u8>(), 0..512)) {
+        let _ = Shortcuts::parse(&data, &mut 0);
+        for format in [VdfFormat::Binary, VdfFormat::Text, VdfFormat::Json] {
+            let _ = KeyValues::read(&data, format);
+        }
+    }
+
+    #[test]
+    fn damaged_shortcuts_never_panic(
+        entries in vec(shortcut(), 1..4),
+        cut in any::<prop::sample::Index>(),
+        flip in any::<(prop::sample::Index, u8)>(),
+    ) {
+        let mut scs = Shortcuts::empty();
+        for sc in entries {
+            scs.push(sc);
+        }
+        let mut bytes = store(&scs);
+        let at = flip.0.index(bytes.len());
+        bytes[at] ^= flip.1;
+        bytes.truncate(cut.index(bytes.len() + 1));
+        if let Ok(scs) = Shortcuts::parse(&bytes, &mut 0) {
+            let _ = store(&scs);
+        }
+        let _ = KeyValues::from_binary(&bytes);
+    }
+}
+
+#[test]
+fn non_utf8_corpus_sample_converts_losslessly() {
+    let bytes = include_bytes!("corpus/non_utf8.vdf");
+    let kv = KeyValues::from_binary(bytes).expect("read");
+    for format in [VdfFormat::Binary, VdfFormat::Text, VdfFormat::Json] {
+        let converted = kv.write(format).expect("write");
+        let back = KeyValues::read(&converted, format).expect("read");
+        assert_eq!(back.to_binary().expect("write"), bytes, "{:?}", format);
+    }
+}
+
+#[test]
+fn non_utf8_strings_read_back_as_their_bytes() {
+    for bytes in [
+        &b"caf\xE9"[..],
+        b"\xFF\xFE",
+        "\u{10FF41}".as_bytes(),
+        b"plain",
+    ] {
+        let s = vdf::string_from_bytes(bytes);
+        assert_eq!(&*vdf::string_to_bytes(&s), bytes);
+    }
+    assert_eq!(
+        vdf::lossy(&vdf::string_from_bytes(b"caf\xE9")),
+        "caf\u{FFFD}"
+    );
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    // Entry "0" whose tags map opens 100 000 nested maps.
+    let mut bytes = b"\x00shortcuts\x00\x000\x00\x00tags\x00".to_vec();
+    bytes.extend(std::iter::repeat_n(b"\x00k\x00", 100_000).flatten());
+    assert!(Shortcuts::parse(&bytes, &mut 0).is_err());
+    assert!(KeyValues::from_binary(&bytes).is_err());
+    assert!(KeyValues::from_text(&"k{".repeat(100_000)).is_err());
+    assert!(KeyValues::read("{\"k\":".repeat(100_000).as_bytes(), VdfFormat::Json).is_err());
+}